@@ -0,0 +1,10 @@
+use runesync_backend::{config::Config, db, osrs::OsrsClient, poll};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = Config::load()?;
+    let store = db::open(&config).await?;
+    let osrs = OsrsClient::from_env()?;
+
+    poll::discover::run(&config, &store, &osrs).await
+}
@@ -0,0 +1,62 @@
+use std::{env, process};
+
+use futures::future;
+use runesync_backend::{config::Config, db, osrs::OsrsClient, poll};
+
+const USAGE: &str = "usage: runesync <poll-stats|poll-top|discover|all|migrate>";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let command = env::args().nth(1).unwrap_or_default();
+    if !["poll-stats", "poll-top", "discover", "all", "migrate"].contains(&command.as_str()) {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let config = Config::load()?;
+    if command == "migrate" {
+        // Stop running pollers first; new ones refuse to start until it ends.
+        db::migrate(&config).await?;
+        println!("Migrated");
+        return Ok(());
+    }
+    let store = db::open(&config).await?;
+    // One client for every loop, so they share a rate limit.
+    let osrs = OsrsClient::from_env()?;
+
+    match command.as_str() {
+        "poll-stats" => poll::stats::run(&store, &osrs).await,
+        "poll-top" => poll::top_players::run(&config, &store, &osrs).await,
+        "discover" => poll::discover::run(&config, &store, &osrs).await,
+        _ => {
+            // Each loop gets its own task so they can run on separate threads.
+            let (stats_store, stats_osrs) = (store.clone(), osrs.clone());
+            let (top_config, top_store, top_osrs) = (config.clone(), store.clone(), osrs.clone());
+            let loops = [
+                (
+                    "poll-stats",
+                    tokio::spawn(async move { poll::stats::run(&stats_store, &stats_osrs).await }),
+                ),
+                (
+                    "poll-top",
+                    tokio::spawn(async move {
+                        poll::top_players::run(&top_config, &top_store, &top_osrs).await
+                    }),
+                ),
+                (
+                    "discover",
+                    tokio::spawn(async move { poll::discover::run(&config, &store, &osrs).await }),
+                ),
+            ];
+            let (names, handles): (Vec<_>, Vec<_>) = loops.into_iter().unzip();
+
+            // The loops only return on failure, which ends the process.
+            let (result, i, _) = future::select_all(handles).await;
+            match result {
+                Ok(Ok(())) => Err(format!("{} stopped", names[i]).into()),
+                Ok(Err(err)) => Err(format!("{} failed: {}", names[i], err).into()),
+                Err(err) => Err(format!("{} panicked: {}", names[i], err).into()),
+            }
+        }
+    }
+}
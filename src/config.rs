@@ -0,0 +1,174 @@
+use std::{env, fs, str::FromStr};
+
+use mongodb::{Client, Collection, Database};
+use serde::Deserialize;
+
+use crate::osrs::GameMode;
+
+/// Where the pollers keep their data. Read from the TOML file named by
+/// `RUNESYNC_CONFIG`, if any, with `RUNESYNC_*` and `GAME_MODE` environment
+/// variables taking precedence.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub store: StoreKind,
+    /// Database file for the SQLite store.
+    pub sqlite_path: String,
+    /// Connection string for the Postgres store, e.g.
+    /// `host=localhost user=runesync dbname=runesync`.
+    pub postgres_url: String,
+    /// MongoDB database name.
+    pub database: String,
+    /// MongoDB collection names. The SQL stores have fixed table names.
+    pub collections: Collections,
+    /// Game mode the leaderboard pollers work on, and the namespace when
+    /// `namespace_by_game_mode` is set.
+    pub game_mode: GameMode,
+    /// Suffix every collection with the game mode, e.g. `stats_seasonal`, so
+    /// each game mode's data is kept apart. Only MongoDB is namespaced; the
+    /// SQL stores key every table by game mode.
+    ///
+    /// Without it, run pollers for a single game mode per database: top
+    /// players are swapped in by replacing the whole collection, so two
+    /// modes refreshing at once can undo each other's refresh.
+    pub namespace_by_game_mode: bool,
+}
+
+/// The database behind the pollers.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StoreKind {
+    Mongo,
+    Sqlite,
+    Postgres,
+}
+
+impl FromStr for StoreKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mongo" => Ok(StoreKind::Mongo),
+            "sqlite" => Ok(StoreKind::Sqlite),
+            "postgres" => Ok(StoreKind::Postgres),
+            _ => Err(format!("unknown store {}", s)),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Collections {
+    pub usernames: String,
+    pub stats: String,
+    pub latest_stats: String,
+    pub top_players: String,
+    /// Prefix of the collections new top players are staged in before
+    /// being swapped in, one per game mode.
+    pub top_players_staging: String,
+    pub top_players_history: String,
+    pub crawls: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            store: StoreKind::Mongo,
+            sqlite_path: "runesync.db".to_string(),
+            postgres_url: "host=localhost user=postgres dbname=runesync".to_string(),
+            database: "test".to_string(),
+            collections: Collections::default(),
+            // Everything was seasonal before game modes were configurable.
+            game_mode: GameMode::Seasonal,
+            namespace_by_game_mode: false,
+        }
+    }
+}
+
+impl Default for Collections {
+    fn default() -> Self {
+        Collections {
+            usernames: "usernames".to_string(),
+            stats: "stats".to_string(),
+            latest_stats: "latestStats".to_string(),
+            top_players: "topPlayers".to_string(),
+            top_players_staging: "topPlayersStaging".to_string(),
+            top_players_history: "topPlayersHistory".to_string(),
+            crawls: "crawls".to_string(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Config, Box<dyn std::error::Error + Send + Sync>> {
+        let mut config: Config = match env::var("RUNESYNC_CONFIG") {
+            Ok(path) => toml::from_str(&fs::read_to_string(path)?)?,
+            Err(_) => Config::default(),
+        };
+
+        if let Ok(store) = env::var("RUNESYNC_STORE") {
+            config.store = store.parse()?;
+        }
+        if let Ok(sqlite_path) = env::var("RUNESYNC_SQLITE_PATH") {
+            config.sqlite_path = sqlite_path;
+        }
+        if let Ok(postgres_url) = env::var("RUNESYNC_POSTGRES_URL") {
+            config.postgres_url = postgres_url;
+        }
+        if let Ok(database) = env::var("RUNESYNC_DATABASE") {
+            config.database = database;
+        }
+        if let Ok(game_mode) = env::var("GAME_MODE") {
+            config.game_mode = game_mode.parse()?;
+        }
+        if let Ok(namespace) = env::var("RUNESYNC_NAMESPACE_BY_GAME_MODE") {
+            config.namespace_by_game_mode = namespace.parse()?;
+        }
+        let collections = &mut config.collections;
+        for (var, name) in [
+            ("RUNESYNC_USERNAMES_COLLECTION", &mut collections.usernames),
+            ("RUNESYNC_STATS_COLLECTION", &mut collections.stats),
+            (
+                "RUNESYNC_LATEST_STATS_COLLECTION",
+                &mut collections.latest_stats,
+            ),
+            (
+                "RUNESYNC_TOP_PLAYERS_COLLECTION",
+                &mut collections.top_players,
+            ),
+            (
+                "RUNESYNC_TOP_PLAYERS_STAGING_COLLECTION",
+                &mut collections.top_players_staging,
+            ),
+            (
+                "RUNESYNC_TOP_PLAYERS_HISTORY_COLLECTION",
+                &mut collections.top_players_history,
+            ),
+            ("RUNESYNC_CRAWLS_COLLECTION", &mut collections.crawls),
+        ] {
+            if let Ok(value) = env::var(var) {
+                *name = value;
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn database(&self, client: &Client) -> Database {
+        client.database(&self.database)
+    }
+
+    /// The full name of a collection from `collections`, namespaced if
+    /// configured.
+    pub fn collection_name(&self, name: &str) -> String {
+        if self.namespace_by_game_mode {
+            format!("{}_{}", name, self.game_mode)
+        } else {
+            name.to_string()
+        }
+    }
+
+    pub fn collection<T>(&self, client: &Client, name: &str) -> Collection<T> {
+        self.database(client)
+            .collection(&self.collection_name(name))
+    }
+}
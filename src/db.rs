@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use crate::{
+    config::{Config, StoreKind},
+    osrs::{self, Category, GameMode, Leaderboard},
+};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+pub use memory::MemoryStore;
+pub use mongo::MongoStore;
+pub use postgres::PostgresStore;
+pub use sqlite::SqliteStore;
+pub use store::{Store, StoreError};
+
+pub mod leaderboard_history;
+pub mod memory;
+pub mod mongo;
+pub mod postgres;
+pub mod sqlite;
+mod store;
+
+/// Brings the configured store's existing data up to date. The SQL stores
+/// migrate their schema whenever they are opened, so this only does work for
+/// MongoDB.
+pub async fn migrate(config: &Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match config.store {
+        StoreKind::Mongo => MongoStore::migrate(config).await,
+        StoreKind::Sqlite | StoreKind::Postgres => open(config).await.map(|_| ()),
+    }
+}
+
+/// Opens the configured store.
+pub async fn open(
+    config: &Config,
+) -> Result<Arc<dyn Store>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(match config.store {
+        StoreKind::Mongo => Arc::new(MongoStore::open(config).await?),
+        StoreKind::Sqlite => Arc::new(SqliteStore::open(&config.sqlite_path)?),
+        StoreKind::Postgres => Arc::new(PostgresStore::connect(&config.postgres_url).await?),
+    })
+}
+
+/// Documents written before game modes were tracked all came from the
+/// seasonal hiscores.
+pub(crate) fn legacy_game_mode() -> GameMode {
+    GameMode::Seasonal
+}
+
+/// Top players written before other tables were tracked were all league
+/// points.
+fn legacy_category() -> Category {
+    Leaderboard::LEAGUE_POINTS.category
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UsernameEntry {
+    pub display_name: String,
+    #[serde(default = "legacy_game_mode")]
+    pub game_mode: GameMode,
+    /// Set when the hiscores stop knowing the player, e.g. after a name
+    /// change or a ban.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_since: Option<DateTime>,
+    /// When a poll last found different stats.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_changed_at: Option<DateTime>,
+    /// When the player is next due to be polled; unset means now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_poll_at: Option<DateTime>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountEntry {
+    pub account_hash: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatEntry {
+    pub timestamp: DateTime,
+    pub display_name: String,
+    #[serde(default = "legacy_game_mode")]
+    pub game_mode: GameMode,
+    pub stats: osrs::Hiscore,
+}
+
+/// Hash of a player's latest stats snapshot, so new stats can be compared
+/// without reading the snapshot back.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestStatsEntry {
+    pub display_name: String,
+    pub game_mode: GameMode,
+    pub hash: i64,
+}
+
+/// A hash of `stats` that is stable across builds, unlike `std`'s hashers.
+pub fn stats_hash(stats: &osrs::Hiscore) -> i64 {
+    // FNV-1a over the JSON, whose field and key order is fixed.
+    let json = serde_json::to_vec(stats).expect("hiscores serialize to JSON");
+    let hash = json.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    hash as i64
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TopPlayerEntry {
+    pub display_name: String,
+    #[serde(default = "legacy_game_mode")]
+    pub game_mode: GameMode,
+    #[serde(default = "legacy_category")]
+    pub category: Category,
+    #[serde(default)]
+    pub table: u32,
+    /// Rank as shown on the hiscores page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<u64>,
+    /// The table's score column: xp for skills, league points for the
+    /// default table. Older documents call it `leaguePoints`.
+    #[serde(alias = "leaguePoints")]
+    pub score: u64,
+    /// Level, for skill tables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<u64>,
+}
+
+impl TopPlayerEntry {
+    pub fn leaderboard(&self) -> Leaderboard {
+        Leaderboard {
+            category: self.category,
+            table: self.table,
+        }
+    }
+}
+
+/// Progress of one pass of the username crawler over a leaderboard. A crawl
+/// without `finishedAt` is resumed from `page` after a restart.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub game_mode: GameMode,
+    pub category: Category,
+    pub table: u32,
+    /// Next page to fetch.
+    pub page: u64,
+    pub started_at: DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime>,
+    #[serde(default)]
+    pub pages_done: u64,
+    /// Usernames this crawl added to `usernames`.
+    #[serde(default)]
+    pub new_names: u64,
+}
+
+/// When to poll a player next, as written by `Store::schedule`.
+#[derive(Debug, Clone)]
+pub struct ScheduledPoll {
+    pub display_name: String,
+    pub game_mode: GameMode,
+    pub last_changed_at: DateTime,
+    pub next_poll_at: DateTime,
+}
+
+/// Outcome of `upsert_usernames`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UpsertCounts {
+    /// Names that were not in `usernames` yet.
+    pub inserted: u64,
+    /// Names that were already known.
+    pub matched: u64,
+}
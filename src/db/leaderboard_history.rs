@@ -0,0 +1,206 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::osrs::{Category, GameMode, Leaderboard};
+
+/// One player's place on the leaderboard in a single refresh. Every entry of
+/// a refresh shares its `snapshotAt`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TopPlayerSnapshotEntry {
+    pub snapshot_at: DateTime,
+    pub game_mode: GameMode,
+    pub category: Category,
+    pub table: u32,
+    pub rank: u64,
+    pub display_name: String,
+    /// Xp for skill tables, league points for the default table.
+    #[serde(alias = "leaguePoints")]
+    pub score: u64,
+}
+
+impl TopPlayerSnapshotEntry {
+    pub fn leaderboard(&self) -> Leaderboard {
+        Leaderboard {
+            category: self.category,
+            table: self.table,
+        }
+    }
+}
+
+/// A player's rank in one snapshot.
+#[derive(Debug, Clone)]
+pub struct RankPoint {
+    pub snapshot_at: DateTime,
+    pub rank: u64,
+    pub score: u64,
+}
+
+/// How far a player moved between two snapshots. Positive `climbed` means a
+/// better rank.
+#[derive(Debug, Clone)]
+pub struct Climber {
+    pub display_name: String,
+    pub from_rank: u64,
+    pub to_rank: u64,
+    pub climbed: i64,
+}
+
+/// Players on both snapshots ordered by how many ranks they gained from
+/// `before` to `after`, best first.
+pub fn climbers(
+    before: Vec<TopPlayerSnapshotEntry>,
+    after: Vec<TopPlayerSnapshotEntry>,
+    limit: usize,
+) -> Vec<Climber> {
+    let before: HashMap<String, u64> = before
+        .into_iter()
+        .map(|entry| (entry.display_name, entry.rank))
+        .collect();
+
+    let mut climbers: Vec<Climber> = after
+        .into_iter()
+        .filter_map(|entry| {
+            let from_rank = *before.get(&entry.display_name)?;
+            Some(Climber {
+                climbed: from_rank as i64 - entry.rank as i64,
+                display_name: entry.display_name,
+                from_rank,
+                to_rank: entry.rank,
+            })
+        })
+        .collect();
+    climbers.sort_by_key(|climber| Reverse(climber.climbed));
+    climbers.truncate(limit);
+    climbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{memory::MemoryStore, Store};
+
+    const BOSSES: Leaderboard = Leaderboard {
+        category: Category::Activity,
+        table: 5,
+    };
+
+    fn entry(
+        snapshot_at: i64,
+        leaderboard: Leaderboard,
+        rank: u64,
+        display_name: &str,
+    ) -> TopPlayerSnapshotEntry {
+        TopPlayerSnapshotEntry {
+            snapshot_at: DateTime::from_millis(snapshot_at),
+            game_mode: GameMode::Seasonal,
+            category: leaderboard.category,
+            table: leaderboard.table,
+            rank,
+            display_name: display_name.to_string(),
+            score: 1_000 - rank,
+        }
+    }
+
+    /// Two league point refreshes, the later one written first, and a
+    /// boss table refresh in between.
+    async fn store() -> MemoryStore {
+        let points = Leaderboard::LEAGUE_POINTS;
+        let store = MemoryStore::default();
+        for snapshot in [
+            vec![
+                entry(2_000, points, 1, "Climber"),
+                entry(2_000, points, 2, "Steady"),
+                entry(2_000, points, 3, "Faller"),
+                entry(2_000, points, 4, "Newcomer"),
+                entry(2_000, points, 5, "Slow"),
+            ],
+            vec![entry(1_500, BOSSES, 1, "Steady")],
+            vec![
+                entry(1_000, points, 2, "Steady"),
+                entry(1_000, points, 1, "Faller"),
+                entry(1_000, points, 9, "Climber"),
+                entry(1_000, points, 6, "Slow"),
+                entry(1_000, points, 7, "Dropped"),
+            ],
+        ] {
+            store.insert_top_players_snapshot(&snapshot).await.unwrap();
+        }
+        store
+    }
+
+    #[tokio::test]
+    async fn climbers_are_best_first_and_limited() {
+        let store = store().await;
+        let (from, to) = (DateTime::from_millis(1_000), DateTime::from_millis(2_000));
+
+        let climbers = store
+            .biggest_climbers(GameMode::Seasonal, Leaderboard::LEAGUE_POINTS, from, to, 3)
+            .await
+            .unwrap();
+        let moves: Vec<(&str, i64)> = climbers
+            .iter()
+            .map(|climber| (climber.display_name.as_str(), climber.climbed))
+            .collect();
+        assert_eq!(moves, [("Climber", 8), ("Slow", 1), ("Steady", 0)]);
+        assert_eq!((climbers[0].from_rank, climbers[0].to_rank), (9, 1));
+    }
+
+    #[tokio::test]
+    async fn climbers_skip_players_missing_from_either_snapshot() {
+        let store = store().await;
+        let (from, to) = (DateTime::from_millis(1_000), DateTime::from_millis(2_000));
+
+        let climbers = store
+            .biggest_climbers(GameMode::Seasonal, Leaderboard::LEAGUE_POINTS, from, to, 10)
+            .await
+            .unwrap();
+        let names: Vec<&str> = climbers
+            .iter()
+            .map(|climber| climber.display_name.as_str())
+            .collect();
+        assert_eq!(names, ["Climber", "Slow", "Steady", "Faller"]);
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_listed_oldest_first_per_leaderboard() {
+        let store = store().await;
+
+        let times = store
+            .snapshots(GameMode::Seasonal, Leaderboard::LEAGUE_POINTS)
+            .await
+            .unwrap();
+        assert_eq!(
+            times,
+            [DateTime::from_millis(1_000), DateTime::from_millis(2_000)]
+        );
+    }
+
+    #[tokio::test]
+    async fn rank_history_is_oldest_first_per_leaderboard() {
+        let store = store().await;
+
+        let history = store
+            .rank_history(GameMode::Seasonal, Leaderboard::LEAGUE_POINTS, "Steady")
+            .await
+            .unwrap();
+        let points: Vec<(i64, u64)> = history
+            .iter()
+            .map(|point| (point.snapshot_at.timestamp_millis(), point.rank))
+            .collect();
+        assert_eq!(points, [(1_000, 2), (2_000, 2)]);
+
+        let bosses = store
+            .rank_history(GameMode::Seasonal, BOSSES, "Steady")
+            .await
+            .unwrap();
+        assert_eq!(bosses.len(), 1);
+        assert!(store
+            .rank_history(GameMode::Regular, Leaderboard::LEAGUE_POINTS, "Steady")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
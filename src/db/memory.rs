@@ -0,0 +1,272 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use super::{
+    leaderboard_history::{RankPoint, TopPlayerSnapshotEntry},
+    CrawlEntry, ScheduledPoll, StatEntry, Store, StoreError, TopPlayerEntry, UpsertCounts,
+    UsernameEntry,
+};
+use crate::osrs::{GameMode, Leaderboard};
+
+/// Keeps everything in memory, for running the pollers without a database.
+#[derive(Default)]
+pub struct MemoryStore {
+    data: Mutex<MemoryData>,
+}
+
+/// The contents of a `MemoryStore`, one `Vec` per collection.
+#[derive(Default, Clone)]
+pub struct MemoryData {
+    pub usernames: Vec<UsernameEntry>,
+    pub stats: Vec<StatEntry>,
+    pub latest_stats: HashMap<(String, GameMode), i64>,
+    pub top_players: Vec<TopPlayerEntry>,
+    pub top_players_history: Vec<TopPlayerSnapshotEntry>,
+    pub crawls: Vec<CrawlEntry>,
+}
+
+impl MemoryStore {
+    pub fn new(data: MemoryData) -> MemoryStore {
+        MemoryStore {
+            data: Mutex::new(data),
+        }
+    }
+
+    /// Locks the store for inspecting or editing its contents.
+    pub fn data(&self) -> MutexGuard<'_, MemoryData> {
+        self.data.lock().unwrap()
+    }
+
+    fn username<'a>(
+        data: &'a mut MemoryData,
+        display_name: &str,
+        game_mode: GameMode,
+    ) -> Option<&'a mut UsernameEntry> {
+        data.usernames
+            .iter_mut()
+            .find(|entry| entry.display_name == display_name && entry.game_mode == game_mode)
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn upsert_usernames(
+        &self,
+        game_mode: GameMode,
+        names: &[&str],
+    ) -> Result<UpsertCounts, StoreError> {
+        let mut data = self.data();
+        let mut counts = UpsertCounts::default();
+        for name in names {
+            if MemoryStore::username(&mut data, name, game_mode).is_some() {
+                counts.matched += 1;
+                continue;
+            }
+            data.usernames.push(UsernameEntry {
+                display_name: name.to_string(),
+                game_mode,
+                missing_since: None,
+                last_changed_at: None,
+                next_poll_at: None,
+            });
+            counts.inserted += 1;
+        }
+        Ok(counts)
+    }
+
+    async fn due_usernames(&self, now: DateTime) -> Result<Vec<UsernameEntry>, StoreError> {
+        Ok(self
+            .data()
+            .usernames
+            .iter()
+            .filter(|entry| entry.next_poll_at.is_none_or(|at| at <= now))
+            .cloned()
+            .collect())
+    }
+
+    async fn set_missing_since(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        missing_since: Option<DateTime>,
+    ) -> Result<(), StoreError> {
+        if let Some(entry) = MemoryStore::username(&mut self.data(), display_name, game_mode) {
+            entry.missing_since = missing_since;
+        }
+        Ok(())
+    }
+
+    async fn schedule(&self, polls: &[ScheduledPoll]) -> Result<(), StoreError> {
+        let mut data = self.data();
+        for poll in polls {
+            if let Some(entry) =
+                MemoryStore::username(&mut data, &poll.display_name, poll.game_mode)
+            {
+                entry.last_changed_at = Some(poll.last_changed_at);
+                entry.next_poll_at = Some(poll.next_poll_at);
+            }
+        }
+        Ok(())
+    }
+
+    async fn latest_snapshot(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+    ) -> Result<Option<StatEntry>, StoreError> {
+        Ok(self
+            .data()
+            .stats
+            .iter()
+            .filter(|entry| entry.display_name == display_name && entry.game_mode == game_mode)
+            .max_by_key(|entry| entry.timestamp)
+            .cloned())
+    }
+
+    async fn insert_snapshot(&self, entry: &StatEntry) -> Result<(), StoreError> {
+        self.data().stats.push(entry.clone());
+        Ok(())
+    }
+
+    async fn latest_stats_hashes(&self) -> Result<HashMap<(String, GameMode), i64>, StoreError> {
+        Ok(self.data().latest_stats.clone())
+    }
+
+    async fn set_latest_stats_hash(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        hash: i64,
+    ) -> Result<(), StoreError> {
+        self.data()
+            .latest_stats
+            .insert((display_name.to_string(), game_mode), hash);
+        Ok(())
+    }
+
+    async fn replace_top_players(
+        &self,
+        game_mode: GameMode,
+        leaderboards: &[Leaderboard],
+        entries: &[TopPlayerEntry],
+    ) -> Result<(), StoreError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut data = self.data();
+        data.top_players.retain(|entry| {
+            entry.game_mode != game_mode || !leaderboards.contains(&entry.leaderboard())
+        });
+        data.top_players.extend_from_slice(entries);
+        Ok(())
+    }
+
+    async fn insert_top_players_snapshot(
+        &self,
+        entries: &[TopPlayerSnapshotEntry],
+    ) -> Result<(), StoreError> {
+        self.data().top_players_history.extend_from_slice(entries);
+        Ok(())
+    }
+
+    async fn snapshots(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Vec<DateTime>, StoreError> {
+        let mut times: Vec<DateTime> = self
+            .data()
+            .top_players_history
+            .iter()
+            .filter(|entry| entry.game_mode == game_mode && entry.leaderboard() == leaderboard)
+            .map(|entry| entry.snapshot_at)
+            .collect();
+        times.sort();
+        times.dedup();
+        Ok(times)
+    }
+
+    async fn snapshot(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        snapshot_at: DateTime,
+    ) -> Result<Vec<TopPlayerSnapshotEntry>, StoreError> {
+        Ok(self
+            .data()
+            .top_players_history
+            .iter()
+            .filter(|entry| {
+                entry.game_mode == game_mode
+                    && entry.leaderboard() == leaderboard
+                    && entry.snapshot_at == snapshot_at
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn rank_history(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        display_name: &str,
+    ) -> Result<Vec<RankPoint>, StoreError> {
+        let mut points: Vec<RankPoint> = self
+            .data()
+            .top_players_history
+            .iter()
+            .filter(|entry| {
+                entry.game_mode == game_mode
+                    && entry.leaderboard() == leaderboard
+                    && entry.display_name == display_name
+            })
+            .map(|entry| RankPoint {
+                snapshot_at: entry.snapshot_at,
+                rank: entry.rank,
+                score: entry.score,
+            })
+            .collect();
+        points.sort_by_key(|point| point.snapshot_at);
+        Ok(points)
+    }
+
+    async fn unfinished_crawl(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Option<CrawlEntry>, StoreError> {
+        Ok(self
+            .data()
+            .crawls
+            .iter()
+            .filter(|crawl| {
+                crawl.game_mode == game_mode
+                    && crawl.category == leaderboard.category
+                    && crawl.table == leaderboard.table
+                    && crawl.finished_at.is_none()
+            })
+            .max_by_key(|crawl| crawl.started_at)
+            .cloned())
+    }
+
+    async fn save_crawl(&self, crawl: &mut CrawlEntry) -> Result<(), StoreError> {
+        let mut data = self.data();
+        match crawl.id {
+            Some(id) => {
+                if let Some(saved) = data.crawls.iter_mut().find(|saved| saved.id == Some(id)) {
+                    *saved = crawl.clone();
+                }
+            }
+            None => {
+                crawl.id = Some(ObjectId::new());
+                data.crawls.push(crawl.clone());
+            }
+        }
+        Ok(())
+    }
+}
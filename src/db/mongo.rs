@@ -0,0 +1,776 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, Bson, DateTime, Document},
+    error::{ErrorKind, WriteFailure},
+    options::{
+        CreateCollectionOptions, FindOneOptions, FindOptions, IndexOptions, TimeseriesGranularity,
+        TimeseriesOptions, UpdateOptions,
+    },
+    results::CollectionType,
+    Client, Collection, Database, IndexModel,
+};
+
+use super::{
+    leaderboard_history::{RankPoint, TopPlayerSnapshotEntry},
+    legacy_game_mode, CrawlEntry, LatestStatsEntry, ScheduledPoll, StatEntry, Store, StoreError,
+    TopPlayerEntry, UpsertCounts, UsernameEntry,
+};
+use crate::{
+    config::Config,
+    osrs::{
+        layout::{HiscoreLayout, MetricKind},
+        GameMode, Leaderboard,
+    },
+};
+
+/// Stores everything in the configured MongoDB database.
+#[derive(Clone)]
+pub struct MongoStore {
+    client: Client,
+    db: Database,
+    usernames: Collection<UsernameEntry>,
+    stats: Collection<StatEntry>,
+    latest_stats: Collection<LatestStatsEntry>,
+    top_players: String,
+    top_players_staging: String,
+    top_players_history: Collection<TopPlayerSnapshotEntry>,
+    crawls: Collection<CrawlEntry>,
+}
+
+impl MongoStore {
+    pub fn new(config: &Config, client: Client) -> MongoStore {
+        let collections = &config.collections;
+        MongoStore {
+            db: config.database(&client),
+            usernames: config.collection(&client, &collections.usernames),
+            stats: config.collection(&client, &collections.stats),
+            latest_stats: config.collection(&client, &collections.latest_stats),
+            top_players: config.collection_name(&collections.top_players),
+            top_players_staging: collections.top_players_staging.clone(),
+            top_players_history: config.collection(&client, &collections.top_players_history),
+            crawls: config.collection(&client, &collections.crawls),
+            client,
+        }
+    }
+
+    /// Connects to `MONGODB_URI` and tags documents from before game modes
+    /// with the legacy mode. Converting `stats` to a time-series collection
+    /// waits for `migrate`, and nothing opens during a migration.
+    pub async fn open(
+        config: &Config,
+    ) -> Result<MongoStore, Box<dyn std::error::Error + Send + Sync>> {
+        let store = MongoStore::new(config, connect().await?);
+        let migrating = store
+            .db
+            .collection::<Document>(MIGRATION_LOCK)
+            .find_one(None, None)
+            .await?;
+        if migrating.is_some() {
+            return Err("a migration is in progress, try again once it finishes".into());
+        }
+        // Untagged documents would be missed by every per-mode query, so
+        // they can't wait for `migrate`.
+        backfill_game_mode(&store.usernames).await?;
+        match collection_type(&store.db, store.stats.name()).await? {
+            None => create_time_series(&store.db, store.stats.name()).await?,
+            Some(CollectionType::Timeseries) => {}
+            Some(_) => {
+                backfill_game_mode(&store.stats).await?;
+                println!(
+                    "{} is not a time-series collection yet, run `runesync migrate`",
+                    store.stats.name()
+                );
+            }
+        }
+        if !config.namespace_by_game_mode {
+            let other_mode = store
+                .db
+                .collection::<Document>(&store.top_players)
+                .find_one(
+                    doc! { "gameMode": { "$exists": true, "$ne": config.game_mode.as_str() } },
+                    None,
+                )
+                .await?;
+            if other_mode.is_some() {
+                println!(
+                    "{} has top players from another game mode; refreshes can overwrite each \
+                     other unless namespace_by_game_mode is set",
+                    store.top_players
+                );
+            }
+        }
+        store.create_indexes().await?;
+        Ok(store)
+    }
+
+    /// Brings documents from older versions up to date. Only one process
+    /// migrates at a time; the others fail while the lock is held.
+    pub async fn migrate(config: &Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let store = MongoStore::new(config, connect().await?);
+        let lock = store.db.collection::<Document>(MIGRATION_LOCK);
+        let locked = lock
+            .insert_one(doc! { "_id": "migrate", "lockedAt": DateTime::now() }, None)
+            .await;
+        match locked {
+            Ok(_) => {}
+            Err(err) if is_duplicate_key(&err) => {
+                return Err(format!(
+                    "another migration holds the lock in {}; delete it if that migration died",
+                    MIGRATION_LOCK
+                )
+                .into())
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let migrated = store.migrate_locked().await;
+        lock.delete_one(doc! { "_id": "migrate" }, None).await?;
+        migrated?;
+        store.create_indexes().await?;
+        Ok(())
+    }
+
+    async fn migrate_locked(&self) -> mongodb::error::Result<()> {
+        backfill_game_mode(&self.usernames).await?;
+        // Time-series documents cannot be rewritten in place, so the older
+        // migrations run before the collection is converted.
+        match collection_type(&self.db, self.stats.name()).await? {
+            Some(CollectionType::Timeseries) => return Ok(()),
+            Some(_) => {
+                backfill_game_mode(&self.stats).await?;
+                let migrated = migrate_stats_to_int64(&self.stats).await?;
+                if migrated > 0 {
+                    println!("Migrated {} stats documents to 64-bit values", migrated);
+                }
+            }
+            None => {}
+        }
+        migrate_stats_to_time_series(&self.client, &self.db, self.stats.name()).await
+    }
+
+    async fn create_indexes(&self) -> mongodb::error::Result<()> {
+        self.usernames
+            .create_indexes(
+                [
+                    IndexModel::builder()
+                        .keys(doc! { "displayName": 1, "gameMode": 1 })
+                        .build(),
+                    IndexModel::builder().keys(doc! { "nextPollAt": 1 }).build(),
+                ],
+                None,
+            )
+            .await?;
+        self.stats
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "displayName": 1, "gameMode": 1, "timestamp": -1 })
+                    .build(),
+                None,
+            )
+            .await?;
+        self.latest_stats
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "displayName": 1, "gameMode": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await?;
+        self.db
+            .collection::<TopPlayerEntry>(&self.top_players)
+            .create_index(top_players_index(), None)
+            .await?;
+        // History is read a snapshot or a player at a time.
+        self.top_players_history
+            .create_indexes(
+                [
+                    IndexModel::builder()
+                        .keys(doc! { "gameMode": 1, "category": 1, "table": 1, "snapshotAt": 1 })
+                        .build(),
+                    IndexModel::builder()
+                        .keys(doc! {
+                            "gameMode": 1,
+                            "category": 1,
+                            "table": 1,
+                            "displayName": 1,
+                            "snapshotAt": 1,
+                        })
+                        .build(),
+                ],
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Narrows `filter` to one leaderboard of one game mode in the history.
+fn history_filter(game_mode: GameMode, leaderboard: Leaderboard, mut filter: Document) -> Document {
+    filter.insert("gameMode", game_mode.as_str());
+    filter.insert("category", leaderboard.category.as_str());
+    filter.insert("table", leaderboard.table);
+    filter
+}
+
+/// Leaderboards are read a table at a time in rank order.
+fn top_players_index() -> IndexModel {
+    IndexModel::builder()
+        .keys(doc! { "gameMode": 1, "category": 1, "table": 1, "rank": 1 })
+        .build()
+}
+
+async fn rename_collection(
+    client: &Client,
+    db: &Database,
+    from: &str,
+    to: &str,
+    drop_target: bool,
+) -> mongodb::error::Result<()> {
+    client
+        .database("admin")
+        .run_command(
+            doc! {
+                "renameCollection": format!("{}.{}", db.name(), from),
+                "to": format!("{}.{}", db.name(), to),
+                "dropTarget": drop_target,
+            },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Holds the document of the migration in progress, if any.
+const MIGRATION_LOCK: &str = "migrationLock";
+
+fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    matches!(
+        &*err.kind,
+        ErrorKind::Write(WriteFailure::WriteError(err)) if err.code == 11000
+    )
+}
+
+/// The type of the collection called `name`, if it exists.
+async fn collection_type(
+    db: &Database,
+    name: &str,
+) -> mongodb::error::Result<Option<CollectionType>> {
+    Ok(db
+        .list_collections(doc! { "name": name }, None)
+        .await?
+        .try_next()
+        .await?
+        .map(|spec| spec.collection_type))
+}
+
+/// Creates `name` as a time-series collection of snapshots per player,
+/// unless another process just did.
+async fn create_time_series(db: &Database, name: &str) -> mongodb::error::Result<()> {
+    let options = CreateCollectionOptions::builder()
+        .timeseries(
+            TimeseriesOptions::builder()
+                .time_field("timestamp".to_string())
+                .meta_field(Some("displayName".to_string()))
+                // Players are polled at most every 15 minutes.
+                .granularity(Some(TimeseriesGranularity::Hours))
+                .build(),
+        )
+        .build();
+    match db.create_collection(name, options).await {
+        Err(err) if matches!(&*err.kind, ErrorKind::Command(err) if err.code == 48) => Ok(()),
+        created => created,
+    }
+}
+
+/// Makes `stats` a time-series collection. An existing plain collection is
+/// copied into a new one and kept as `<stats>_plain`. Safe to run again
+/// after an interruption, but only while holding the migration lock.
+async fn migrate_stats_to_time_series(
+    client: &Client,
+    db: &Database,
+    stats: &str,
+) -> mongodb::error::Result<()> {
+    let staging_name = format!("{}_timeseries", stats);
+    let backup = format!("{}_plain", stats);
+    if collection_type(db, stats).await?.is_none() {
+        let staging = collection_type(db, &staging_name).await?;
+        if staging.is_some() && collection_type(db, &backup).await?.is_some() {
+            // Interrupted between the renames below, after the copy finished.
+            rename_collection(client, db, &staging_name, stats, false).await?;
+            println!("Finished migrating {} to a time-series collection", stats);
+            return Ok(());
+        }
+        return create_time_series(db, stats).await;
+    }
+
+    println!("Migrating {} to a time-series collection...", stats);
+    // Left over if a previous migration was interrupted during the copy.
+    let staging = db.collection::<Document>(&staging_name);
+    staging.drop(None).await?;
+    create_time_series(db, &staging_name).await?;
+
+    let mut cursor = db.collection::<Document>(stats).find(None, None).await?;
+    let mut batch = Vec::new();
+    let mut copied = 0;
+    while let Some(document) = cursor.try_next().await? {
+        batch.push(document);
+        if batch.len() == 1000 {
+            copied += batch.len();
+            staging.insert_many(batch.drain(..), None).await?;
+        }
+    }
+    if !batch.is_empty() {
+        copied += batch.len();
+        staging.insert_many(batch, None).await?;
+    }
+
+    rename_collection(client, db, stats, &backup, false).await?;
+    rename_collection(client, db, &staging_name, stats, false).await?;
+    println!(
+        "Migrated {} stats documents, the old collection is kept as {}",
+        copied, backup
+    );
+    Ok(())
+}
+
+fn player_filter(display_name: &str, game_mode: GameMode) -> Document {
+    doc! {
+        "displayName": display_name,
+        "gameMode": game_mode.as_str(),
+    }
+}
+
+#[async_trait]
+impl Store for MongoStore {
+    async fn upsert_usernames(
+        &self,
+        game_mode: GameMode,
+        names: &[&str],
+    ) -> Result<UpsertCounts, StoreError> {
+        upsert_usernames(&self.db, &self.usernames, game_mode, names).await
+    }
+
+    async fn due_usernames(&self, now: DateTime) -> Result<Vec<UsernameEntry>, StoreError> {
+        let due = doc! {
+            "$or": [
+                { "nextPollAt": { "$lte": now } },
+                { "nextPollAt": { "$exists": false } },
+            ]
+        };
+        Ok(self.usernames.find(due, None).await?.try_collect().await?)
+    }
+
+    async fn set_missing_since(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        missing_since: Option<DateTime>,
+    ) -> Result<(), StoreError> {
+        let update = match missing_since {
+            Some(missing_since) => doc! { "$set": { "missingSince": missing_since } },
+            None => doc! { "$unset": { "missingSince": "" } },
+        };
+        self.usernames
+            .update_one(player_filter(display_name, game_mode), update, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn schedule(&self, polls: &[ScheduledPoll]) -> Result<(), StoreError> {
+        if polls.is_empty() {
+            return Ok(());
+        }
+        let updates: Vec<Document> = polls
+            .iter()
+            .map(|poll| {
+                doc! {
+                    "q": player_filter(&poll.display_name, poll.game_mode),
+                    "u": {
+                        "$set": {
+                            "lastChangedAt": poll.last_changed_at,
+                            "nextPollAt": poll.next_poll_at,
+                        }
+                    },
+                }
+            })
+            .collect();
+        let response = self
+            .db
+            .run_command(
+                doc! {
+                    "update": self.usernames.name(),
+                    "updates": updates,
+                    "ordered": false,
+                },
+                None,
+            )
+            .await?;
+        write_errors(&response, "schedule")
+    }
+
+    async fn latest_snapshot(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+    ) -> Result<Option<StatEntry>, StoreError> {
+        Ok(self
+            .stats
+            .find_one(
+                player_filter(display_name, game_mode),
+                FindOneOptions::builder()
+                    .sort(doc! { "timestamp": -1 })
+                    .build(),
+            )
+            .await?)
+    }
+
+    async fn insert_snapshot(&self, entry: &StatEntry) -> Result<(), StoreError> {
+        self.stats.insert_one(entry, None).await?;
+        Ok(())
+    }
+
+    async fn latest_stats_hashes(&self) -> Result<HashMap<(String, GameMode), i64>, StoreError> {
+        let mut cursor = self.latest_stats.find(None, None).await?;
+        let mut hashes = HashMap::new();
+        while let Some(entry) = cursor.try_next().await? {
+            hashes.insert((entry.display_name, entry.game_mode), entry.hash);
+        }
+        Ok(hashes)
+    }
+
+    async fn set_latest_stats_hash(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        hash: i64,
+    ) -> Result<(), StoreError> {
+        self.latest_stats
+            .update_one(
+                player_filter(display_name, game_mode),
+                doc! { "$set": { "hash": hash } },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_top_players(
+        &self,
+        game_mode: GameMode,
+        leaderboards: &[Leaderboard],
+        entries: &[TopPlayerEntry],
+    ) -> Result<(), StoreError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        // Stage the new leaderboards next to the live ones, then rename over
+        // them. Indexes move with the collection. The swap replaces the whole
+        // collection, so a concurrent refresh for another game mode sharing
+        // it would be lost; see `Config::namespace_by_game_mode`.
+        let live = self.db.collection::<TopPlayerEntry>(&self.top_players);
+        let staging = self
+            .db
+            .collection::<TopPlayerEntry>(&format!("{}_{}", self.top_players_staging, game_mode));
+        staging.drop(None).await?;
+        staging.create_index(top_players_index(), None).await?;
+
+        // Carry over everything this refresh does not replace. Entries from
+        // before game modes were recorded are left behind.
+        let replaced: Vec<Document> = leaderboards
+            .iter()
+            .map(|leaderboard| {
+                doc! {
+                    "gameMode": game_mode.as_str(),
+                    "category": leaderboard.category.as_str(),
+                    "table": leaderboard.table,
+                }
+            })
+            .collect();
+        let mut kept = doc! { "gameMode": { "$exists": true } };
+        if !replaced.is_empty() {
+            kept.insert("$nor", replaced);
+        }
+        live.aggregate(
+            [
+                doc! { "$match": kept },
+                doc! { "$merge": { "into": staging.name() } },
+            ],
+            None,
+        )
+        .await?;
+
+        staging.insert_many(entries, None).await?;
+        rename_collection(
+            &self.client,
+            &self.db,
+            staging.name(),
+            &self.top_players,
+            true,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn insert_top_players_snapshot(
+        &self,
+        entries: &[TopPlayerSnapshotEntry],
+    ) -> Result<(), StoreError> {
+        if !entries.is_empty() {
+            self.top_players_history.insert_many(entries, None).await?;
+        }
+        Ok(())
+    }
+
+    async fn snapshots(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Vec<DateTime>, StoreError> {
+        let mut times: Vec<DateTime> = self
+            .top_players_history
+            .distinct(
+                "snapshotAt",
+                history_filter(game_mode, leaderboard, doc! {}),
+                None,
+            )
+            .await?
+            .into_iter()
+            .filter_map(|time| time.as_datetime().copied())
+            .collect();
+        times.sort();
+        Ok(times)
+    }
+
+    async fn snapshot(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        snapshot_at: DateTime,
+    ) -> Result<Vec<TopPlayerSnapshotEntry>, StoreError> {
+        Ok(self
+            .top_players_history
+            .find(
+                history_filter(game_mode, leaderboard, doc! { "snapshotAt": snapshot_at }),
+                None,
+            )
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn rank_history(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        display_name: &str,
+    ) -> Result<Vec<RankPoint>, StoreError> {
+        let entries: Vec<TopPlayerSnapshotEntry> = self
+            .top_players_history
+            .find(
+                history_filter(game_mode, leaderboard, doc! { "displayName": display_name }),
+                FindOptions::builder()
+                    .sort(doc! { "snapshotAt": 1 })
+                    .build(),
+            )
+            .await?
+            .try_collect()
+            .await?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| RankPoint {
+                snapshot_at: entry.snapshot_at,
+                rank: entry.rank,
+                score: entry.score,
+            })
+            .collect())
+    }
+
+    async fn unfinished_crawl(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Option<CrawlEntry>, StoreError> {
+        Ok(self
+            .crawls
+            .find_one(
+                doc! {
+                    "gameMode": game_mode.as_str(),
+                    "category": leaderboard.category.as_str(),
+                    "table": leaderboard.table,
+                    "finishedAt": { "$exists": false },
+                },
+                FindOneOptions::builder()
+                    .sort(doc! { "startedAt": -1 })
+                    .build(),
+            )
+            .await?)
+    }
+
+    async fn save_crawl(&self, crawl: &mut CrawlEntry) -> Result<(), StoreError> {
+        match crawl.id {
+            Some(id) => {
+                self.crawls
+                    .replace_one(doc! { "_id": id }, &*crawl, None)
+                    .await?;
+            }
+            None => {
+                crawl.id = self
+                    .crawls
+                    .insert_one(&*crawl, None)
+                    .await?
+                    .inserted_id
+                    .as_object_id();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Connects to the deployment at `MONGODB_URI`.
+pub async fn connect() -> Result<Client, Box<dyn std::error::Error + Send + Sync>> {
+    let mongodb_url = std::env::var("MONGODB_URI")?;
+    Ok(Client::with_uri_str(mongodb_url).await?)
+}
+
+/// Tags documents written before game modes were tracked so that filters on
+/// `gameMode` still match them.
+async fn backfill_game_mode<T>(collection: &Collection<T>) -> mongodb::error::Result<()> {
+    collection
+        .update_many(
+            doc! { "gameMode": { "$exists": false } },
+            doc! { "$set": { "gameMode": legacy_game_mode().as_str() } },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Rewrites every skill and activity number in `stats` as a 64-bit integer so
+/// documents written with 32-bit fields compare and sort like new ones.
+async fn migrate_stats_to_int64(stats: &Collection<StatEntry>) -> mongodb::error::Result<u64> {
+    let mut set = doc! {};
+    let mut needs_migration = Vec::new();
+    for entry in HiscoreLayout::current().entries {
+        if entry.kind != MetricKind::Skill {
+            continue;
+        }
+        for field in ["xp", "level", "rank"] {
+            let path = format!("stats.skills.{}.{}", entry.key, field);
+            // Unranked skills have no rank; `$$REMOVE` keeps it absent.
+            set.insert(
+                path.clone(),
+                doc! { "$ifNull": [{ "$toLong": format!("${}", path) }, "$$REMOVE"] },
+            );
+            needs_migration.push(doc! { path: { "$type": ["int", "double"] } });
+        }
+    }
+    set.insert(
+        "stats.activities",
+        doc! {
+            "$arrayToObject": {
+                "$map": {
+                    "input": { "$objectToArray": "$stats.activities" },
+                    "as": "activity",
+                    "in": {
+                        "k": "$$activity.k",
+                        "v": {
+                            "$cond": [
+                                { "$eq": ["$$activity.v", null] },
+                                null,
+                                {
+                                    "score": { "$toLong": "$$activity.v.score" },
+                                    "rank": { "$toLong": "$$activity.v.rank" },
+                                },
+                            ]
+                        },
+                    },
+                }
+            }
+        },
+    );
+
+    let result = stats
+        .update_many(
+            doc! { "$or": needs_migration },
+            vec![doc! { "$set": set }],
+            None,
+        )
+        .await?;
+    Ok(result.modified_count)
+}
+
+/// Fails with the `writeErrors` of a bulk write command's `response`, if any.
+fn write_errors(response: &Document, what: &str) -> Result<(), StoreError> {
+    let Ok(errors) = response.get_array("writeErrors") else {
+        return Ok(());
+    };
+    let messages: Vec<&str> = errors
+        .iter()
+        .filter_map(Bson::as_document)
+        .filter_map(|error| error.get_str("errmsg").ok())
+        .collect();
+    Err(StoreError::Write(format!(
+        "{} {} writes failed: {}",
+        errors.len(),
+        what,
+        messages.join("; ")
+    )))
+}
+
+/// Upserts `names` into `usernames` with a single unordered `update` command,
+/// so one failed name does not stop the rest.
+async fn upsert_usernames(
+    db: &Database,
+    usernames: &Collection<UsernameEntry>,
+    game_mode: GameMode,
+    names: &[&str],
+) -> Result<UpsertCounts, StoreError> {
+    if names.is_empty() {
+        return Ok(UpsertCounts {
+            inserted: 0,
+            matched: 0,
+        });
+    }
+
+    let updates: Vec<Document> = names
+        .iter()
+        .map(|name| {
+            doc! {
+                "q": { "displayName": *name, "gameMode": game_mode.as_str() },
+                "u": { "$set": { "displayName": *name } },
+                "upsert": true,
+            }
+        })
+        .collect();
+    let response = db
+        .run_command(
+            doc! {
+                "update": usernames.name(),
+                "updates": updates,
+                "ordered": false,
+            },
+            None,
+        )
+        .await?;
+
+    write_errors(&response, "username")?;
+
+    let total = match response.get("n") {
+        Some(Bson::Int32(n)) => *n as u64,
+        Some(Bson::Int64(n)) => *n as u64,
+        _ => 0,
+    };
+    let inserted = response
+        .get_array("upserted")
+        .map(|upserted| upserted.len() as u64)
+        .unwrap_or(0);
+    Ok(UpsertCounts {
+        inserted,
+        matched: total.saturating_sub(inserted),
+    })
+}
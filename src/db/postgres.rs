@@ -0,0 +1,693 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde_json::{json, Map, Value};
+use tokio_postgres::{Client, NoTls, Row};
+
+use super::{
+    leaderboard_history::{RankPoint, TopPlayerSnapshotEntry},
+    CrawlEntry, ScheduledPoll, StatEntry, Store, StoreError, TopPlayerEntry, UpsertCounts,
+    UsernameEntry,
+};
+use crate::osrs::{Category, GameMode, Hiscore, Leaderboard};
+
+/// Schema changes, applied in order and recorded in `schema_migrations`.
+/// Released entries are never edited.
+const MIGRATIONS: &[&str] = &[
+    // Each snapshot is one row per skill or activity, so gains and rankings
+    // are plain SQL. Skills fill `level` and `xp`, activities `score`; an
+    // activity the player is unranked in has neither `rank` nor `score`.
+    "
+    CREATE TABLE usernames (
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        missing_since TIMESTAMPTZ,
+        last_changed_at TIMESTAMPTZ,
+        next_poll_at TIMESTAMPTZ,
+        PRIMARY KEY (display_name, game_mode)
+    );
+    CREATE INDEX usernames_next_poll_at ON usernames (next_poll_at);
+
+    CREATE TABLE stats (
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        timestamp TIMESTAMPTZ NOT NULL,
+        category TEXT NOT NULL,
+        metric TEXT NOT NULL,
+        rank BIGINT,
+        level BIGINT,
+        xp BIGINT,
+        score BIGINT,
+        PRIMARY KEY (display_name, game_mode, timestamp, metric)
+    );
+    CREATE INDEX stats_metric ON stats (game_mode, metric, timestamp);
+
+    CREATE TABLE top_players (
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        leaderboard TEXT NOT NULL,
+        rank BIGINT,
+        score BIGINT NOT NULL,
+        level BIGINT
+    );
+
+    CREATE TABLE top_players_history (
+        snapshot_at TIMESTAMPTZ NOT NULL,
+        game_mode TEXT NOT NULL,
+        leaderboard TEXT NOT NULL,
+        rank BIGINT NOT NULL,
+        display_name TEXT NOT NULL,
+        score BIGINT NOT NULL
+    );
+    CREATE INDEX top_players_history_snapshot
+        ON top_players_history (game_mode, leaderboard, snapshot_at);
+
+    CREATE TABLE crawls (
+        id TEXT PRIMARY KEY,
+        game_mode TEXT NOT NULL,
+        leaderboard TEXT NOT NULL,
+        page BIGINT NOT NULL,
+        started_at TIMESTAMPTZ NOT NULL,
+        finished_at TIMESTAMPTZ,
+        pages_done BIGINT NOT NULL,
+        new_names BIGINT NOT NULL
+    );
+    ",
+    "
+    CREATE TABLE latest_stats (
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        hash BIGINT NOT NULL,
+        PRIMARY KEY (display_name, game_mode)
+    );
+    ",
+    "
+    CREATE INDEX top_players_history_player
+        ON top_players_history (game_mode, leaderboard, display_name, snapshot_at);
+    ",
+];
+
+/// Stores everything in PostgreSQL, with stats snapshots split into one row
+/// per metric.
+pub struct PostgresStore {
+    client: Client,
+}
+
+impl PostgresStore {
+    /// Connects with `url` and applies any pending migrations.
+    pub async fn connect(url: &str) -> Result<PostgresStore, StoreError> {
+        let (mut client, connection) = tokio_postgres::connect(url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                println!("Postgres connection failed: {}", err);
+            }
+        });
+        migrate(&mut client).await?;
+        Ok(PostgresStore { client })
+    }
+}
+
+/// Key of the advisory lock held while migrating, so that processes starting
+/// together apply each migration once.
+const MIGRATION_LOCK: i64 = 0x72756e6573796e63;
+
+async fn migrate(client: &mut Client) -> Result<(), tokio_postgres::Error> {
+    loop {
+        let tx = client.transaction().await?;
+        tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])
+            .await?;
+        tx.batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (version INTEGER PRIMARY KEY)",
+        )
+        .await?;
+        let applied: i32 = tx
+            .query_one(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+                &[],
+            )
+            .await?
+            .get(0);
+        let Some(migration) = MIGRATIONS.get(applied as usize) else {
+            return tx.commit().await;
+        };
+        let version = applied + 1;
+        tx.batch_execute(migration).await?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version) VALUES ($1)",
+            &[&version],
+        )
+        .await?;
+        tx.commit().await?;
+        println!("Applied Postgres migration {}", version);
+    }
+}
+
+fn timestamp(at: DateTime) -> chrono::DateTime<Utc> {
+    Utc.timestamp_millis_opt(at.timestamp_millis()).unwrap()
+}
+
+fn datetime(at: chrono::DateTime<Utc>) -> DateTime {
+    DateTime::from_millis(at.timestamp_millis())
+}
+
+fn parsed<T: std::str::FromStr<Err = String>>(row: &Row, column: &str) -> Result<T, StoreError> {
+    row.get::<_, &str>(column)
+        .parse()
+        .map_err(StoreError::Corrupt)
+}
+
+fn username_entry(row: &Row) -> Result<UsernameEntry, StoreError> {
+    Ok(UsernameEntry {
+        display_name: row.get("display_name"),
+        game_mode: parsed(row, "game_mode")?,
+        missing_since: row.get::<_, Option<_>>("missing_since").map(datetime),
+        last_changed_at: row.get::<_, Option<_>>("last_changed_at").map(datetime),
+        next_poll_at: row.get::<_, Option<_>>("next_poll_at").map(datetime),
+    })
+}
+
+fn snapshot_entry(row: &Row) -> Result<TopPlayerSnapshotEntry, StoreError> {
+    let leaderboard: Leaderboard = parsed(row, "leaderboard")?;
+    Ok(TopPlayerSnapshotEntry {
+        snapshot_at: datetime(row.get("snapshot_at")),
+        game_mode: parsed(row, "game_mode")?,
+        category: leaderboard.category,
+        table: leaderboard.table,
+        rank: row.get::<_, i64>("rank") as u64,
+        display_name: row.get("display_name"),
+        score: row.get::<_, i64>("score") as u64,
+    })
+}
+
+fn crawl_entry(row: &Row) -> Result<CrawlEntry, StoreError> {
+    let id = ObjectId::parse_str(row.get::<_, &str>("id"))
+        .map_err(|err| StoreError::Corrupt(err.to_string()))?;
+    let leaderboard: Leaderboard = parsed(row, "leaderboard")?;
+    Ok(CrawlEntry {
+        id: Some(id),
+        game_mode: parsed(row, "game_mode")?,
+        category: leaderboard.category,
+        table: leaderboard.table,
+        page: row.get::<_, i64>("page") as u64,
+        started_at: datetime(row.get("started_at")),
+        finished_at: row.get::<_, Option<_>>("finished_at").map(datetime),
+        pages_done: row.get::<_, i64>("pages_done") as u64,
+        new_names: row.get::<_, i64>("new_names") as u64,
+    })
+}
+
+/// A snapshot's metrics as columns, for inserting with `unnest`.
+#[derive(Default)]
+struct MetricRows {
+    categories: Vec<&'static str>,
+    metrics: Vec<String>,
+    ranks: Vec<Option<i64>>,
+    levels: Vec<Option<i64>>,
+    xps: Vec<Option<i64>>,
+    scores: Vec<Option<i64>>,
+}
+
+impl MetricRows {
+    fn new(stats: &Hiscore) -> Result<MetricRows, StoreError> {
+        let stats =
+            serde_json::to_value(stats).map_err(|err| StoreError::Write(err.to_string()))?;
+        let number = |entry: &Value, field: &str| entry.get(field).and_then(Value::as_i64);
+
+        let mut rows = MetricRows::default();
+        for (category, entries) in [
+            (Category::Skill, &stats["skills"]),
+            (Category::Activity, &stats["activities"]),
+        ] {
+            for (metric, entry) in entries.as_object().into_iter().flatten() {
+                rows.categories.push(category.as_str());
+                rows.metrics.push(metric.clone());
+                rows.ranks.push(number(entry, "rank"));
+                rows.levels.push(number(entry, "level"));
+                rows.xps.push(number(entry, "xp"));
+                rows.scores.push(number(entry, "score"));
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// Puts a snapshot's metric rows back together.
+fn hiscore(rows: &[Row]) -> Result<Hiscore, StoreError> {
+    let mut skills = Map::new();
+    let mut activities = Map::new();
+    for row in rows {
+        let metric: String = row.get("metric");
+        let rank: Option<i64> = row.get("rank");
+        if row.get::<_, &str>("category") == Category::Skill.as_str() {
+            let mut entry = json!({
+                "xp": row.get::<_, Option<i64>>("xp"),
+                "level": row.get::<_, Option<i64>>("level"),
+            });
+            if let Some(rank) = rank {
+                entry["rank"] = rank.into();
+            }
+            skills.insert(metric, entry);
+        } else {
+            let entry = match row.get::<_, Option<i64>>("score") {
+                Some(score) => json!({ "score": score, "rank": rank }),
+                None => Value::Null,
+            };
+            activities.insert(metric, entry);
+        }
+    }
+    serde_json::from_value(json!({ "skills": skills, "activities": activities }))
+        .map_err(|err| StoreError::Corrupt(err.to_string()))
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn upsert_usernames(
+        &self,
+        game_mode: GameMode,
+        names: &[&str],
+    ) -> Result<UpsertCounts, StoreError> {
+        let inserted = self
+            .client
+            .execute(
+                "INSERT INTO usernames (display_name, game_mode)
+                 SELECT unnest($1::text[]), $2
+                 ON CONFLICT DO NOTHING",
+                &[&names, &game_mode.as_str()],
+            )
+            .await?;
+        Ok(UpsertCounts {
+            inserted,
+            matched: names.len() as u64 - inserted,
+        })
+    }
+
+    async fn due_usernames(&self, now: DateTime) -> Result<Vec<UsernameEntry>, StoreError> {
+        self.client
+            .query(
+                "SELECT * FROM usernames WHERE next_poll_at IS NULL OR next_poll_at <= $1",
+                &[&timestamp(now)],
+            )
+            .await?
+            .iter()
+            .map(username_entry)
+            .collect()
+    }
+
+    async fn set_missing_since(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        missing_since: Option<DateTime>,
+    ) -> Result<(), StoreError> {
+        self.client
+            .execute(
+                "UPDATE usernames SET missing_since = $3 WHERE display_name = $1 AND game_mode = $2",
+                &[
+                    &display_name,
+                    &game_mode.as_str(),
+                    &missing_since.map(timestamp),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn schedule(&self, polls: &[ScheduledPoll]) -> Result<(), StoreError> {
+        let display_names: Vec<&str> = polls.iter().map(|p| p.display_name.as_str()).collect();
+        let game_modes: Vec<&str> = polls.iter().map(|p| p.game_mode.as_str()).collect();
+        let last_changed_ats: Vec<_> = polls.iter().map(|p| timestamp(p.last_changed_at)).collect();
+        let next_poll_ats: Vec<_> = polls.iter().map(|p| timestamp(p.next_poll_at)).collect();
+        self.client
+            .execute(
+                "UPDATE usernames
+                 SET last_changed_at = polls.last_changed_at, next_poll_at = polls.next_poll_at
+                 FROM unnest($1::text[], $2::text[], $3::timestamptz[], $4::timestamptz[])
+                     AS polls (display_name, game_mode, last_changed_at, next_poll_at)
+                 WHERE usernames.display_name = polls.display_name
+                     AND usernames.game_mode = polls.game_mode",
+                &[
+                    &display_names,
+                    &game_modes,
+                    &last_changed_ats,
+                    &next_poll_ats,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn latest_snapshot(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+    ) -> Result<Option<StatEntry>, StoreError> {
+        let rows = self
+            .client
+            .query(
+                "SELECT * FROM stats
+                 WHERE display_name = $1 AND game_mode = $2 AND timestamp = (
+                     SELECT MAX(timestamp) FROM stats WHERE display_name = $1 AND game_mode = $2
+                 )",
+                &[&display_name, &game_mode.as_str()],
+            )
+            .await?;
+        let Some(first) = rows.first() else {
+            return Ok(None);
+        };
+        Ok(Some(StatEntry {
+            timestamp: datetime(first.get("timestamp")),
+            display_name: display_name.to_string(),
+            game_mode,
+            stats: hiscore(&rows)?,
+        }))
+    }
+
+    async fn insert_snapshot(&self, entry: &StatEntry) -> Result<(), StoreError> {
+        let rows = MetricRows::new(&entry.stats)?;
+        self.client
+            .execute(
+                "INSERT INTO stats
+                 (display_name, game_mode, timestamp, category, metric, rank, level, xp, score)
+                 SELECT $1, $2, $3, * FROM unnest(
+                     $4::text[], $5::text[], $6::bigint[], $7::bigint[], $8::bigint[], $9::bigint[]
+                 )",
+                &[
+                    &entry.display_name,
+                    &entry.game_mode.as_str(),
+                    &timestamp(entry.timestamp),
+                    &rows.categories,
+                    &rows.metrics,
+                    &rows.ranks,
+                    &rows.levels,
+                    &rows.xps,
+                    &rows.scores,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn latest_stats_hashes(&self) -> Result<HashMap<(String, GameMode), i64>, StoreError> {
+        self.client
+            .query("SELECT * FROM latest_stats", &[])
+            .await?
+            .iter()
+            .map(|row| {
+                let key = (row.get("display_name"), parsed(row, "game_mode")?);
+                Ok((key, row.get("hash")))
+            })
+            .collect()
+    }
+
+    async fn set_latest_stats_hash(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        hash: i64,
+    ) -> Result<(), StoreError> {
+        self.client
+            .execute(
+                "INSERT INTO latest_stats (display_name, game_mode, hash) VALUES ($1, $2, $3)
+                 ON CONFLICT (display_name, game_mode) DO UPDATE SET hash = EXCLUDED.hash",
+                &[&display_name, &game_mode.as_str(), &hash],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn replace_top_players(
+        &self,
+        game_mode: GameMode,
+        leaderboards: &[Leaderboard],
+        entries: &[TopPlayerEntry],
+    ) -> Result<(), StoreError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let replaced: Vec<String> = leaderboards.iter().map(Leaderboard::to_string).collect();
+        let display_names: Vec<&str> = entries.iter().map(|e| e.display_name.as_str()).collect();
+        let game_modes: Vec<&str> = entries.iter().map(|e| e.game_mode.as_str()).collect();
+        let entry_leaderboards: Vec<String> = entries
+            .iter()
+            .map(|e| e.leaderboard().to_string())
+            .collect();
+        let ranks: Vec<Option<i64>> = entries.iter().map(|e| e.rank.map(|r| r as i64)).collect();
+        let scores: Vec<i64> = entries.iter().map(|e| e.score as i64).collect();
+        let levels: Vec<Option<i64>> = entries.iter().map(|e| e.level.map(|l| l as i64)).collect();
+        // A single statement, so readers never see the leaderboards half
+        // replaced.
+        self.client
+            .execute(
+                "WITH cleared AS (
+                     DELETE FROM top_players WHERE game_mode = $7 AND leaderboard = ANY($8)
+                 )
+                 INSERT INTO top_players (display_name, game_mode, leaderboard, rank, score, level)
+                 SELECT * FROM unnest(
+                     $1::text[], $2::text[], $3::text[], $4::bigint[], $5::bigint[], $6::bigint[]
+                 )",
+                &[
+                    &display_names,
+                    &game_modes,
+                    &entry_leaderboards,
+                    &ranks,
+                    &scores,
+                    &levels,
+                    &game_mode.as_str(),
+                    &replaced,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_top_players_snapshot(
+        &self,
+        entries: &[TopPlayerSnapshotEntry],
+    ) -> Result<(), StoreError> {
+        let snapshot_ats: Vec<_> = entries.iter().map(|e| timestamp(e.snapshot_at)).collect();
+        let game_modes: Vec<&str> = entries.iter().map(|e| e.game_mode.as_str()).collect();
+        let leaderboards: Vec<String> = entries
+            .iter()
+            .map(|e| e.leaderboard().to_string())
+            .collect();
+        let ranks: Vec<i64> = entries.iter().map(|e| e.rank as i64).collect();
+        let display_names: Vec<&str> = entries.iter().map(|e| e.display_name.as_str()).collect();
+        let scores: Vec<i64> = entries.iter().map(|e| e.score as i64).collect();
+        self.client
+            .execute(
+                "INSERT INTO top_players_history
+                 (snapshot_at, game_mode, leaderboard, rank, display_name, score)
+                 SELECT * FROM unnest(
+                     $1::timestamptz[], $2::text[], $3::text[], $4::bigint[], $5::text[],
+                     $6::bigint[]
+                 )",
+                &[
+                    &snapshot_ats,
+                    &game_modes,
+                    &leaderboards,
+                    &ranks,
+                    &display_names,
+                    &scores,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn snapshots(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Vec<DateTime>, StoreError> {
+        let rows = self
+            .client
+            .query(
+                "SELECT DISTINCT snapshot_at FROM top_players_history
+                 WHERE game_mode = $1 AND leaderboard = $2
+                 ORDER BY snapshot_at",
+                &[&game_mode.as_str(), &leaderboard.to_string()],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| datetime(row.get("snapshot_at")))
+            .collect())
+    }
+
+    async fn snapshot(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        snapshot_at: DateTime,
+    ) -> Result<Vec<TopPlayerSnapshotEntry>, StoreError> {
+        self.client
+            .query(
+                "SELECT * FROM top_players_history
+                 WHERE game_mode = $1 AND leaderboard = $2 AND snapshot_at = $3",
+                &[
+                    &game_mode.as_str(),
+                    &leaderboard.to_string(),
+                    &timestamp(snapshot_at),
+                ],
+            )
+            .await?
+            .iter()
+            .map(snapshot_entry)
+            .collect()
+    }
+
+    async fn rank_history(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        display_name: &str,
+    ) -> Result<Vec<RankPoint>, StoreError> {
+        let rows = self
+            .client
+            .query(
+                "SELECT snapshot_at, rank, score FROM top_players_history
+                 WHERE game_mode = $1 AND leaderboard = $2 AND display_name = $3
+                 ORDER BY snapshot_at",
+                &[&game_mode.as_str(), &leaderboard.to_string(), &display_name],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| RankPoint {
+                snapshot_at: datetime(row.get("snapshot_at")),
+                rank: row.get::<_, i64>("rank") as u64,
+                score: row.get::<_, i64>("score") as u64,
+            })
+            .collect())
+    }
+
+    async fn unfinished_crawl(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Option<CrawlEntry>, StoreError> {
+        self.client
+            .query_opt(
+                "SELECT * FROM crawls
+                 WHERE game_mode = $1 AND leaderboard = $2 AND finished_at IS NULL
+                 ORDER BY started_at DESC LIMIT 1",
+                &[&game_mode.as_str(), &leaderboard.to_string()],
+            )
+            .await?
+            .as_ref()
+            .map(crawl_entry)
+            .transpose()
+    }
+
+    async fn save_crawl(&self, crawl: &mut CrawlEntry) -> Result<(), StoreError> {
+        let id = *crawl.id.get_or_insert_with(ObjectId::new);
+        let leaderboard = Leaderboard {
+            category: crawl.category,
+            table: crawl.table,
+        };
+        self.client
+            .execute(
+                "INSERT INTO crawls
+                 (id, game_mode, leaderboard, page, started_at, finished_at, pages_done, new_names)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (id) DO UPDATE SET
+                     page = EXCLUDED.page,
+                     finished_at = EXCLUDED.finished_at,
+                     pages_done = EXCLUDED.pages_done,
+                     new_names = EXCLUDED.new_names",
+                &[
+                    &id.to_hex(),
+                    &crawl.game_mode.as_str(),
+                    &leaderboard.to_string(),
+                    &(crawl.page as i64),
+                    &timestamp(crawl.started_at),
+                    &crawl.finished_at.map(timestamp),
+                    &(crawl.pages_done as i64),
+                    &(crawl.new_names as i64),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Run with `RUNESYNC_TEST_POSTGRES_URL` pointing at a scratch database and
+/// `cargo test -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::lite_body, osrs::layout::HiscoreLayout};
+
+    async fn store() -> PostgresStore {
+        let url = std::env::var("RUNESYNC_TEST_POSTGRES_URL")
+            .expect("RUNESYNC_TEST_POSTGRES_URL is not set");
+        PostgresStore::connect(&url).await.unwrap()
+    }
+
+    /// A name no earlier run has used, since the database is kept between
+    /// runs.
+    fn unique_name(prefix: &str) -> String {
+        format!("{} {}", prefix, DateTime::now().timestamp_millis())
+    }
+
+    /// Stats with an unranked skill, a ranked activity and unranked ones.
+    fn hiscore() -> Hiscore {
+        let layout = HiscoreLayout::current();
+        layout
+            .parse(&lite_body(layout, &[("magic", "-1,-1,-1")]))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn snapshot_round_trip() {
+        let store = store().await;
+        let display_name = unique_name("Snapshot");
+        let entry = StatEntry {
+            timestamp: DateTime::from_millis(1_700_000_000_000),
+            display_name: display_name.clone(),
+            game_mode: GameMode::Seasonal,
+            stats: hiscore(),
+        };
+        store.insert_snapshot(&entry).await.unwrap();
+
+        let latest = store
+            .latest_snapshot(&display_name, GameMode::Seasonal)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest, entry);
+        assert!(latest.stats.skills().get("magic").unwrap().rank().is_none());
+        assert!(latest.stats.activities().get("zulrah").is_none());
+        assert!(store
+            .latest_snapshot(&display_name, GameMode::Regular)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn latest_snapshot_is_the_newest() {
+        let store = store().await;
+        let display_name = unique_name("Newest");
+        for millis in [1_700_000_000_000, 1_700_000_600_000] {
+            let entry = StatEntry {
+                timestamp: DateTime::from_millis(millis),
+                display_name: display_name.clone(),
+                game_mode: GameMode::Seasonal,
+                stats: hiscore(),
+            };
+            store.insert_snapshot(&entry).await.unwrap();
+        }
+
+        let latest = store
+            .latest_snapshot(&display_name, GameMode::Seasonal)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.timestamp.timestamp_millis(), 1_700_000_600_000);
+    }
+}
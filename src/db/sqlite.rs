@@ -0,0 +1,850 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row, TransactionBehavior};
+
+use super::{
+    leaderboard_history::{RankPoint, TopPlayerSnapshotEntry},
+    CrawlEntry, ScheduledPoll, StatEntry, Store, StoreError, TopPlayerEntry, UpsertCounts,
+    UsernameEntry,
+};
+use crate::osrs::{GameMode, Leaderboard};
+
+/// Schema changes in the order they were made. `PRAGMA user_version` counts
+/// the ones a database already has, so new changes go at the end.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE usernames (
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        missing_since INTEGER,
+        last_changed_at INTEGER,
+        next_poll_at INTEGER,
+        PRIMARY KEY (display_name, game_mode)
+    );
+    CREATE INDEX usernames_next_poll_at ON usernames (next_poll_at);
+
+    CREATE TABLE stats (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        stats TEXT NOT NULL
+    );
+    CREATE INDEX stats_player ON stats (display_name, game_mode, timestamp);
+
+    CREATE TABLE top_players (
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        leaderboard TEXT NOT NULL,
+        rank INTEGER,
+        score INTEGER NOT NULL,
+        level INTEGER
+    );
+
+    CREATE TABLE top_players_history (
+        snapshot_at INTEGER NOT NULL,
+        game_mode TEXT NOT NULL,
+        leaderboard TEXT NOT NULL,
+        rank INTEGER NOT NULL,
+        display_name TEXT NOT NULL,
+        score INTEGER NOT NULL
+    );
+    CREATE INDEX top_players_history_snapshot
+        ON top_players_history (game_mode, leaderboard, snapshot_at);
+
+    CREATE TABLE crawls (
+        id TEXT PRIMARY KEY,
+        game_mode TEXT NOT NULL,
+        leaderboard TEXT NOT NULL,
+        page INTEGER NOT NULL,
+        started_at INTEGER NOT NULL,
+        finished_at INTEGER,
+        pages_done INTEGER NOT NULL,
+        new_names INTEGER NOT NULL
+    );
+    ",
+    "
+    CREATE TABLE latest_stats (
+        display_name TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        hash INTEGER NOT NULL,
+        PRIMARY KEY (display_name, game_mode)
+    );
+    ",
+    "
+    CREATE INDEX top_players_history_player
+        ON top_players_history (game_mode, leaderboard, display_name, snapshot_at);
+    ",
+];
+
+/// Stores everything in a single SQLite file, for deployments without a
+/// database server.
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Opens or creates the database at `path` and applies any pending
+    /// migrations.
+    pub fn open(path: &str) -> Result<SqliteStore, StoreError> {
+        let mut conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn)?;
+        Ok(SqliteStore {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Runs `f` on tokio's blocking threads, so queries waiting on the disk
+    /// or the lock never hold up the runtime.
+    async fn call<T, F>(&self, f: F) -> Result<T, StoreError>
+    where
+        F: FnOnce(&mut Connection) -> Result<T, StoreError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        match tokio::task::spawn_blocking(move || f(&mut conn.lock().unwrap())).await {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    loop {
+        // Taking the write lock before reading the version makes other
+        // processes starting on the same file wait, then find it applied.
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: usize = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let Some(migration) = MIGRATIONS.get(version) else {
+            return Ok(());
+        };
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", version + 1)?;
+        tx.commit()?;
+        println!("Applied SQLite migration {}", version + 1);
+    }
+}
+
+/// Reads a text column through `FromStr`.
+fn parsed<T>(row: &Row, idx: usize) -> rusqlite::Result<T>
+where
+    T: FromStr,
+    T::Err: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let text: String = row.get(idx)?;
+    text.parse().map_err(|err: T::Err| {
+        rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, err.into())
+    })
+}
+
+fn datetime(row: &Row, idx: usize) -> rusqlite::Result<DateTime> {
+    Ok(DateTime::from_millis(row.get(idx)?))
+}
+
+fn optional_datetime(row: &Row, idx: usize) -> rusqlite::Result<Option<DateTime>> {
+    Ok(row.get::<_, Option<i64>>(idx)?.map(DateTime::from_millis))
+}
+
+fn username_entry(row: &Row) -> rusqlite::Result<UsernameEntry> {
+    Ok(UsernameEntry {
+        display_name: row.get(0)?,
+        game_mode: parsed(row, 1)?,
+        missing_since: optional_datetime(row, 2)?,
+        last_changed_at: optional_datetime(row, 3)?,
+        next_poll_at: optional_datetime(row, 4)?,
+    })
+}
+
+fn snapshot_entry(row: &Row) -> rusqlite::Result<TopPlayerSnapshotEntry> {
+    let leaderboard: Leaderboard = parsed(row, 2)?;
+    Ok(TopPlayerSnapshotEntry {
+        snapshot_at: datetime(row, 0)?,
+        game_mode: parsed(row, 1)?,
+        category: leaderboard.category,
+        table: leaderboard.table,
+        rank: row.get(3)?,
+        display_name: row.get(4)?,
+        score: row.get(5)?,
+    })
+}
+
+fn crawl_entry(row: &Row) -> rusqlite::Result<CrawlEntry> {
+    let id: String = row.get(0)?;
+    let id = ObjectId::parse_str(&id)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err)))?;
+    let leaderboard: Leaderboard = parsed(row, 2)?;
+    Ok(CrawlEntry {
+        id: Some(id),
+        game_mode: parsed(row, 1)?,
+        category: leaderboard.category,
+        table: leaderboard.table,
+        page: row.get(3)?,
+        started_at: datetime(row, 4)?,
+        finished_at: optional_datetime(row, 5)?,
+        pages_done: row.get(6)?,
+        new_names: row.get(7)?,
+    })
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn upsert_usernames(
+        &self,
+        game_mode: GameMode,
+        names: &[&str],
+    ) -> Result<UpsertCounts, StoreError> {
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            let mut counts = UpsertCounts::default();
+            {
+                let mut insert = tx.prepare(
+                    "INSERT INTO usernames (display_name, game_mode) VALUES (?1, ?2)
+                     ON CONFLICT DO NOTHING",
+                )?;
+                for name in names {
+                    match insert.execute(params![name, game_mode.as_str()])? {
+                        0 => counts.matched += 1,
+                        _ => counts.inserted += 1,
+                    }
+                }
+            }
+            tx.commit()?;
+            Ok(counts)
+        })
+        .await
+    }
+
+    async fn due_usernames(&self, now: DateTime) -> Result<Vec<UsernameEntry>, StoreError> {
+        self.call(move |conn| {
+            let mut due = conn.prepare(
+                "SELECT display_name, game_mode, missing_since, last_changed_at, next_poll_at
+                 FROM usernames WHERE next_poll_at IS NULL OR next_poll_at <= ?1",
+            )?;
+            let entries = due
+                .query_map([now.timestamp_millis()], username_entry)?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(entries)
+        })
+        .await
+    }
+
+    async fn set_missing_since(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        missing_since: Option<DateTime>,
+    ) -> Result<(), StoreError> {
+        let display_name = display_name.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                "UPDATE usernames SET missing_since = ?3
+                 WHERE display_name = ?1 AND game_mode = ?2",
+                params![
+                    display_name,
+                    game_mode.as_str(),
+                    missing_since.map(|at| at.timestamp_millis())
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn schedule(&self, polls: &[ScheduledPoll]) -> Result<(), StoreError> {
+        let polls = polls.to_vec();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut update = tx.prepare(
+                    "UPDATE usernames SET last_changed_at = ?3, next_poll_at = ?4
+                     WHERE display_name = ?1 AND game_mode = ?2",
+                )?;
+                for poll in polls {
+                    update.execute(params![
+                        poll.display_name,
+                        poll.game_mode.as_str(),
+                        poll.last_changed_at.timestamp_millis(),
+                        poll.next_poll_at.timestamp_millis()
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn latest_snapshot(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+    ) -> Result<Option<StatEntry>, StoreError> {
+        let display_name = display_name.to_string();
+        self.call(move |conn| {
+            let entry = conn
+                .query_row(
+                    "SELECT timestamp, stats FROM stats
+                     WHERE display_name = ?1 AND game_mode = ?2
+                     ORDER BY timestamp DESC LIMIT 1",
+                    params![display_name, game_mode.as_str()],
+                    |row| {
+                        let stats: String = row.get(1)?;
+                        Ok(StatEntry {
+                            timestamp: datetime(row, 0)?,
+                            display_name: display_name.clone(),
+                            game_mode,
+                            stats: serde_json::from_str(&stats).map_err(|err| {
+                                rusqlite::Error::FromSqlConversionFailure(
+                                    1,
+                                    Type::Text,
+                                    Box::new(err),
+                                )
+                            })?,
+                        })
+                    },
+                )
+                .optional()?;
+            Ok(entry)
+        })
+        .await
+    }
+
+    async fn insert_snapshot(&self, entry: &StatEntry) -> Result<(), StoreError> {
+        let stats = serde_json::to_string(&entry.stats)
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+        let timestamp = entry.timestamp.timestamp_millis();
+        let display_name = entry.display_name.clone();
+        let game_mode = entry.game_mode;
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT INTO stats (timestamp, display_name, game_mode, stats)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![timestamp, display_name, game_mode.as_str(), stats],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn latest_stats_hashes(&self) -> Result<HashMap<(String, GameMode), i64>, StoreError> {
+        self.call(|conn| {
+            let mut hashes =
+                conn.prepare("SELECT display_name, game_mode, hash FROM latest_stats")?;
+            let hashes = hashes
+                .query_map([], |row| Ok(((row.get(0)?, parsed(row, 1)?), row.get(2)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(hashes)
+        })
+        .await
+    }
+
+    async fn set_latest_stats_hash(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        hash: i64,
+    ) -> Result<(), StoreError> {
+        let display_name = display_name.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT INTO latest_stats (display_name, game_mode, hash) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (display_name, game_mode) DO UPDATE SET hash = excluded.hash",
+                params![display_name, game_mode.as_str(), hash],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn replace_top_players(
+        &self,
+        game_mode: GameMode,
+        leaderboards: &[Leaderboard],
+        entries: &[TopPlayerEntry],
+    ) -> Result<(), StoreError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let leaderboards = leaderboards.to_vec();
+        let entries = entries.to_vec();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut delete = tx
+                    .prepare("DELETE FROM top_players WHERE game_mode = ?1 AND leaderboard = ?2")?;
+                for leaderboard in leaderboards {
+                    delete.execute(params![game_mode.as_str(), leaderboard.to_string()])?;
+                }
+                let mut insert = tx.prepare(
+                    "INSERT INTO top_players
+                     (display_name, game_mode, leaderboard, rank, score, level)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                for entry in entries {
+                    insert.execute(params![
+                        entry.display_name,
+                        entry.game_mode.as_str(),
+                        entry.leaderboard().to_string(),
+                        entry.rank,
+                        entry.score,
+                        entry.level
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn insert_top_players_snapshot(
+        &self,
+        entries: &[TopPlayerSnapshotEntry],
+    ) -> Result<(), StoreError> {
+        let entries = entries.to_vec();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut insert = tx.prepare(
+                    "INSERT INTO top_players_history
+                     (snapshot_at, game_mode, leaderboard, rank, display_name, score)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                for entry in entries {
+                    insert.execute(params![
+                        entry.snapshot_at.timestamp_millis(),
+                        entry.game_mode.as_str(),
+                        entry.leaderboard().to_string(),
+                        entry.rank,
+                        entry.display_name,
+                        entry.score
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn snapshots(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Vec<DateTime>, StoreError> {
+        self.call(move |conn| {
+            let mut snapshots = conn.prepare(
+                "SELECT DISTINCT snapshot_at FROM top_players_history
+                 WHERE game_mode = ?1 AND leaderboard = ?2
+                 ORDER BY snapshot_at",
+            )?;
+            let times = snapshots
+                .query_map(
+                    params![game_mode.as_str(), leaderboard.to_string()],
+                    |row| datetime(row, 0),
+                )?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(times)
+        })
+        .await
+    }
+
+    async fn snapshot(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        snapshot_at: DateTime,
+    ) -> Result<Vec<TopPlayerSnapshotEntry>, StoreError> {
+        self.call(move |conn| {
+            let mut snapshot = conn.prepare(
+                "SELECT snapshot_at, game_mode, leaderboard, rank, display_name, score
+                 FROM top_players_history
+                 WHERE game_mode = ?1 AND leaderboard = ?2 AND snapshot_at = ?3",
+            )?;
+            let entries = snapshot
+                .query_map(
+                    params![
+                        game_mode.as_str(),
+                        leaderboard.to_string(),
+                        snapshot_at.timestamp_millis()
+                    ],
+                    snapshot_entry,
+                )?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(entries)
+        })
+        .await
+    }
+
+    async fn rank_history(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        display_name: &str,
+    ) -> Result<Vec<RankPoint>, StoreError> {
+        let display_name = display_name.to_string();
+        self.call(move |conn| {
+            let mut history = conn.prepare(
+                "SELECT snapshot_at, rank, score FROM top_players_history
+                 WHERE game_mode = ?1 AND leaderboard = ?2 AND display_name = ?3
+                 ORDER BY snapshot_at",
+            )?;
+            let points = history
+                .query_map(
+                    params![game_mode.as_str(), leaderboard.to_string(), display_name],
+                    |row| {
+                        Ok(RankPoint {
+                            snapshot_at: datetime(row, 0)?,
+                            rank: row.get(1)?,
+                            score: row.get(2)?,
+                        })
+                    },
+                )?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(points)
+        })
+        .await
+    }
+
+    async fn unfinished_crawl(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Option<CrawlEntry>, StoreError> {
+        self.call(move |conn| {
+            let crawl = conn
+                .query_row(
+                    "SELECT id, game_mode, leaderboard, page, started_at, finished_at,
+                            pages_done, new_names
+                     FROM crawls
+                     WHERE game_mode = ?1 AND leaderboard = ?2 AND finished_at IS NULL
+                     ORDER BY started_at DESC LIMIT 1",
+                    params![game_mode.as_str(), leaderboard.to_string()],
+                    crawl_entry,
+                )
+                .optional()?;
+            Ok(crawl)
+        })
+        .await
+    }
+
+    async fn save_crawl(&self, crawl: &mut CrawlEntry) -> Result<(), StoreError> {
+        crawl.id.get_or_insert_with(ObjectId::new);
+        let crawl = crawl.clone();
+        self.call(move |conn| {
+            let leaderboard = Leaderboard {
+                category: crawl.category,
+                table: crawl.table,
+            };
+            conn.execute(
+                "INSERT OR REPLACE INTO crawls
+                 (id, game_mode, leaderboard, page, started_at, finished_at, pages_done,
+                  new_names)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    crawl.id.map(|id| id.to_hex()),
+                    crawl.game_mode.as_str(),
+                    leaderboard.to_string(),
+                    crawl.page,
+                    crawl.started_at.timestamp_millis(),
+                    crawl.finished_at.map(|at| at.timestamp_millis()),
+                    crawl.pages_done,
+                    crawl.new_names
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::lite_body,
+        osrs::{layout::HiscoreLayout, Category, Hiscore},
+    };
+
+    const BOSSES: Leaderboard = Leaderboard {
+        category: Category::Activity,
+        table: 5,
+    };
+
+    fn store() -> SqliteStore {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        SqliteStore {
+            conn: Arc::new(Mutex::new(conn)),
+        }
+    }
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn has_table(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = ?1",
+            [name],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    /// Stats with an unranked skill, a ranked activity and unranked ones.
+    fn hiscore() -> Hiscore {
+        let layout = HiscoreLayout::current();
+        layout
+            .parse(&lite_body(layout, &[("magic", "-1,-1,-1")]))
+            .unwrap()
+    }
+
+    fn top_player(
+        display_name: &str,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> TopPlayerEntry {
+        TopPlayerEntry {
+            display_name: display_name.to_string(),
+            game_mode,
+            category: leaderboard.category,
+            table: leaderboard.table,
+            rank: Some(1),
+            score: 10,
+            level: None,
+        }
+    }
+
+    #[test]
+    fn migrates_a_new_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        assert!(has_table(&conn, "latest_stats"));
+        assert!(has_table(&conn, "top_players_history_player"));
+
+        // Nothing is left to apply the second time.
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn upgrades_a_database_from_the_first_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO usernames (display_name, game_mode) VALUES ('Kept', 'seasonal')",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        assert!(has_table(&conn, "latest_stats"));
+        let kept: String = conn
+            .query_row("SELECT display_name FROM usernames", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(kept, "Kept");
+    }
+
+    #[tokio::test]
+    async fn counts_inserted_and_matched_usernames() {
+        let store = store();
+
+        let counts = store
+            .upsert_usernames(GameMode::Seasonal, &["Alice", "Bob"])
+            .await
+            .unwrap();
+        assert_eq!((counts.inserted, counts.matched), (2, 0));
+        let counts = store
+            .upsert_usernames(GameMode::Seasonal, &["Bob", "Carol"])
+            .await
+            .unwrap();
+        assert_eq!((counts.inserted, counts.matched), (1, 1));
+        // The same name in another game mode is another player.
+        let counts = store
+            .upsert_usernames(GameMode::Regular, &["Alice"])
+            .await
+            .unwrap();
+        assert_eq!((counts.inserted, counts.matched), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn scheduled_players_are_due_once_their_time_comes() {
+        let store = store();
+        store
+            .upsert_usernames(GameMode::Seasonal, &["Later", "Now"])
+            .await
+            .unwrap();
+        let now = DateTime::from_millis(1_700_000_000_000);
+        let next_poll_at = DateTime::from_millis(now.timestamp_millis() + 60 * 60 * 1000);
+        store
+            .schedule(&[ScheduledPoll {
+                display_name: "Later".to_string(),
+                game_mode: GameMode::Seasonal,
+                last_changed_at: now,
+                next_poll_at,
+            }])
+            .await
+            .unwrap();
+
+        let due = store.due_usernames(now).await.unwrap();
+        let names: Vec<&str> = due
+            .iter()
+            .map(|entry| entry.display_name.as_str())
+            .collect();
+        assert_eq!(names, ["Now"]);
+
+        let mut due = store.due_usernames(next_poll_at).await.unwrap();
+        due.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].last_changed_at, Some(now));
+        assert_eq!(due[0].next_poll_at, Some(next_poll_at));
+    }
+
+    #[tokio::test]
+    async fn snapshot_round_trip() {
+        let store = store();
+        for millis in [1_700_000_000_000, 1_700_000_600_000] {
+            let entry = StatEntry {
+                timestamp: DateTime::from_millis(millis),
+                display_name: "Player".to_string(),
+                game_mode: GameMode::Seasonal,
+                stats: hiscore(),
+            };
+            store.insert_snapshot(&entry).await.unwrap();
+        }
+
+        let latest = store
+            .latest_snapshot("Player", GameMode::Seasonal)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.timestamp.timestamp_millis(), 1_700_000_600_000);
+        assert_eq!(latest.stats, hiscore());
+        assert!(latest.stats.skills().get("magic").unwrap().rank().is_none());
+        assert!(store
+            .latest_snapshot("Player", GameMode::Regular)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn replaces_only_the_refreshed_leaderboards() {
+        let store = store();
+        store
+            .replace_top_players(
+                GameMode::Seasonal,
+                &[Leaderboard::LEAGUE_POINTS, BOSSES],
+                &[
+                    top_player("Old Leader", GameMode::Seasonal, Leaderboard::LEAGUE_POINTS),
+                    top_player("Old Boss", GameMode::Seasonal, BOSSES),
+                ],
+            )
+            .await
+            .unwrap();
+        store
+            .replace_top_players(
+                GameMode::Regular,
+                &[Leaderboard::LEAGUE_POINTS],
+                &[top_player(
+                    "Main Leader",
+                    GameMode::Regular,
+                    Leaderboard::LEAGUE_POINTS,
+                )],
+            )
+            .await
+            .unwrap();
+
+        store
+            .replace_top_players(
+                GameMode::Seasonal,
+                &[Leaderboard::LEAGUE_POINTS],
+                &[top_player(
+                    "Leader",
+                    GameMode::Seasonal,
+                    Leaderboard::LEAGUE_POINTS,
+                )],
+            )
+            .await
+            .unwrap();
+        // Nothing found leaves the previous leaderboards alone.
+        store
+            .replace_top_players(GameMode::Seasonal, &[BOSSES], &[])
+            .await
+            .unwrap();
+
+        let names: Vec<String> = store
+            .call(|conn| {
+                let mut names =
+                    conn.prepare("SELECT display_name FROM top_players ORDER BY display_name")?;
+                let names = names
+                    .query_map([], |row| row.get(0))?
+                    .collect::<rusqlite::Result<_>>()?;
+                Ok(names)
+            })
+            .await
+            .unwrap();
+        assert_eq!(names, ["Leader", "Main Leader", "Old Boss"]);
+    }
+
+    #[tokio::test]
+    async fn saved_crawls_resume_until_finished() {
+        let store = store();
+        let mut crawl = CrawlEntry {
+            id: None,
+            game_mode: GameMode::Seasonal,
+            category: Leaderboard::LEAGUE_POINTS.category,
+            table: Leaderboard::LEAGUE_POINTS.table,
+            page: 1,
+            started_at: DateTime::from_millis(1_700_000_000_000),
+            finished_at: None,
+            pages_done: 0,
+            new_names: 0,
+        };
+        store.save_crawl(&mut crawl).await.unwrap();
+        assert!(crawl.id.is_some());
+
+        crawl.page = 7;
+        crawl.pages_done = 6;
+        crawl.new_names = 150;
+        store.save_crawl(&mut crawl).await.unwrap();
+        let resumed = store
+            .unfinished_crawl(GameMode::Seasonal, Leaderboard::LEAGUE_POINTS)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed.id, crawl.id);
+        assert_eq!(resumed.started_at, crawl.started_at);
+        assert_eq!(
+            (resumed.page, resumed.pages_done, resumed.new_names),
+            (7, 6, 150)
+        );
+        assert!(store
+            .unfinished_crawl(GameMode::Seasonal, BOSSES)
+            .await
+            .unwrap()
+            .is_none());
+
+        crawl.finished_at = Some(DateTime::from_millis(1_700_000_600_000));
+        store.save_crawl(&mut crawl).await.unwrap();
+        assert!(store
+            .unfinished_crawl(GameMode::Seasonal, Leaderboard::LEAGUE_POINTS)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, fmt};
+
+use async_trait::async_trait;
+use mongodb::bson::DateTime;
+
+use super::{
+    leaderboard_history::{climbers, Climber, RankPoint, TopPlayerSnapshotEntry},
+    CrawlEntry, ScheduledPoll, StatEntry, TopPlayerEntry, UpsertCounts, UsernameEntry,
+};
+use crate::osrs::{GameMode, Leaderboard};
+
+/// Everything the pollers read and write, so they can run against any
+/// database.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Adds `names` to the players to poll, leaving known players alone.
+    async fn upsert_usernames(
+        &self,
+        game_mode: GameMode,
+        names: &[&str],
+    ) -> Result<UpsertCounts, StoreError>;
+
+    /// Players whose next poll is at or before `now`, including players that
+    /// were never scheduled.
+    async fn due_usernames(&self, now: DateTime) -> Result<Vec<UsernameEntry>, StoreError>;
+
+    /// Marks a player as gone from the hiscores, or back on them with `None`.
+    async fn set_missing_since(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        missing_since: Option<DateTime>,
+    ) -> Result<(), StoreError>;
+
+    /// Records when each player's stats last changed and when to poll them
+    /// next, in one batch.
+    async fn schedule(&self, polls: &[ScheduledPoll]) -> Result<(), StoreError>;
+
+    /// The player's most recent stats snapshot.
+    async fn latest_snapshot(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+    ) -> Result<Option<StatEntry>, StoreError>;
+
+    async fn insert_snapshot(&self, entry: &StatEntry) -> Result<(), StoreError>;
+
+    /// `stats_hash` of every player's latest snapshot, where recorded.
+    async fn latest_stats_hashes(&self) -> Result<HashMap<(String, GameMode), i64>, StoreError>;
+
+    /// Records the `stats_hash` of a player's latest snapshot.
+    async fn set_latest_stats_hash(
+        &self,
+        display_name: &str,
+        game_mode: GameMode,
+        hash: i64,
+    ) -> Result<(), StoreError>;
+
+    /// Replaces `game_mode`'s top players on `leaderboards` with `entries` in
+    /// one step, so readers see either the previous leaderboards or the new
+    /// ones. Other game modes and leaderboards are left alone, and empty
+    /// `entries` change nothing.
+    async fn replace_top_players(
+        &self,
+        game_mode: GameMode,
+        leaderboards: &[Leaderboard],
+        entries: &[TopPlayerEntry],
+    ) -> Result<(), StoreError>;
+
+    /// Appends one refresh of the leaderboards to their history.
+    async fn insert_top_players_snapshot(
+        &self,
+        entries: &[TopPlayerSnapshotEntry],
+    ) -> Result<(), StoreError>;
+
+    /// Times of every stored snapshot of `leaderboard`, oldest first.
+    async fn snapshots(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Vec<DateTime>, StoreError>;
+
+    /// Every entry of one snapshot of `leaderboard`.
+    async fn snapshot(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        snapshot_at: DateTime,
+    ) -> Result<Vec<TopPlayerSnapshotEntry>, StoreError>;
+
+    /// A player's rank in every snapshot they appear in, oldest first.
+    async fn rank_history(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        display_name: &str,
+    ) -> Result<Vec<RankPoint>, StoreError>;
+
+    /// Players on both snapshots ordered by how many ranks they gained from
+    /// `from` to `to`, best first.
+    async fn biggest_climbers(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+        from: DateTime,
+        to: DateTime,
+        limit: usize,
+    ) -> Result<Vec<Climber>, StoreError> {
+        let before = self.snapshot(game_mode, leaderboard, from).await?;
+        let after = self.snapshot(game_mode, leaderboard, to).await?;
+        Ok(climbers(before, after, limit))
+    }
+
+    /// The latest crawl of `leaderboard` that has not finished yet.
+    async fn unfinished_crawl(
+        &self,
+        game_mode: GameMode,
+        leaderboard: Leaderboard,
+    ) -> Result<Option<CrawlEntry>, StoreError>;
+
+    /// Saves a crawl checkpoint, assigning an `id` to new crawls.
+    async fn save_crawl(&self, crawl: &mut CrawlEntry) -> Result<(), StoreError>;
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Mongo(mongodb::error::Error),
+    Sqlite(rusqlite::Error),
+    Postgres(tokio_postgres::Error),
+    /// Part of a batch was rejected.
+    Write(String),
+    /// Stored data could not be read back.
+    Corrupt(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Mongo(err) => write!(f, "mongodb: {}", err),
+            StoreError::Sqlite(err) => write!(f, "sqlite: {}", err),
+            StoreError::Postgres(err) => write!(f, "postgres: {}", err),
+            StoreError::Write(message) => write!(f, "write failed: {}", message),
+            StoreError::Corrupt(message) => write!(f, "corrupt data: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Mongo(err) => Some(err),
+            StoreError::Sqlite(err) => Some(err),
+            StoreError::Postgres(err) => Some(err),
+            StoreError::Write(_) | StoreError::Corrupt(_) => None,
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for StoreError {
+    fn from(err: mongodb::error::Error) -> Self {
+        StoreError::Mongo(err)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        StoreError::Sqlite(err)
+    }
+}
+
+impl From<tokio_postgres::Error> for StoreError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        StoreError::Postgres(err)
+    }
+}
@@ -0,0 +1,180 @@
+//! Hiscore bodies, leaderboard pages and a stand-in hiscores server shared by
+//! the tests.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use reqwest::Url;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::osrs::{
+    layout::{HiscoreLayout, MetricKind},
+    rate_limit::RetryPolicy,
+    OsrsClient,
+};
+
+/// `index_lite.ws` for `layout`, every skill ranked at level 50 except where
+/// `overrides` gives an entry's line. Only `clueScrollsAll` is ranked among
+/// the activities.
+pub(crate) fn lite_body(layout: &HiscoreLayout, overrides: &[(&str, &str)]) -> String {
+    let skills = layout
+        .entries
+        .iter()
+        .filter(|entry| entry.kind == MetricKind::Skill && entry.key != "overall")
+        .count() as i64;
+    let mut lines = Vec::new();
+    for entry in layout.entries {
+        let line = match overrides.iter().find(|(key, _)| *key == entry.key) {
+            Some((_, line)) => line.to_string(),
+            None => match (entry.kind, entry.key) {
+                (MetricKind::Skill, "overall") => {
+                    format!("1200,{},{}", skills * 50, skills * 101_333)
+                }
+                (MetricKind::Skill, _) => "5000,50,101333".to_string(),
+                (MetricKind::Activity, "clueScrollsAll") => "800,12".to_string(),
+                _ => "-1,-1".to_string(),
+            },
+        };
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// An activity leaderboard page of `(rank, name, score)` rows, with a next
+/// page arrow to `next_href` if given.
+pub(crate) fn leaderboard_page(rows: &[(u64, &str, u64)], next_href: Option<&str>) -> String {
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|&(rank, name, score)| row(rank, name, &[score]))
+        .collect();
+    page(&rows, next_href)
+}
+
+/// A skill leaderboard page of `(rank, name, level, xp)` rows.
+pub(crate) fn skill_page(rows: &[(u64, &str, u64, u64)], next_href: Option<&str>) -> String {
+    let rows: Vec<String> = rows
+        .iter()
+        .map(|&(rank, name, level, xp)| row(rank, name, &[level, xp]))
+        .collect();
+    page(&rows, next_href)
+}
+
+/// A row as the hiscores write it, with a non-breaking space for each space
+/// in `name` and commas between thousands.
+fn row(rank: u64, name: &str, numbers: &[u64]) -> String {
+    let numbers: String = numbers
+        .iter()
+        .map(|&number| format!(r#"<td class="right">{}</td>"#, grouped(number)))
+        .collect();
+    format!(
+        r#"<tr class="personal-hiscores__row"><td class="right">{}</td><td class="left"><a href="hiscorepersonal?user1={}">{}</a></td>{}</tr>"#,
+        grouped(rank),
+        name.replace(' ', "%A0"),
+        name.replace(' ', "&nbsp;"),
+        numbers
+    )
+}
+
+fn page(rows: &[String], next_href: Option<&str>) -> String {
+    let arrow = match next_href {
+        Some(href) => format!(
+            r#"<a class="personal-hiscores__pagination-arrow personal-hiscores__pagination-arrow--down" href="{}"></a>"#,
+            href
+        ),
+        None => String::new(),
+    };
+    format!(
+        "<html><body><table>{}</table>{}</body></html>",
+        rows.concat(),
+        arrow
+    )
+}
+
+fn grouped(number: u64) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// A request the stand-in server received.
+pub(crate) struct Request {
+    pub query: HashMap<String, String>,
+}
+
+/// Serves every request with `respond`, which returns a status and body.
+/// Returns the server's base URL and the log of requests it has seen.
+pub(crate) async fn serve(
+    respond: impl Fn(&Request) -> (u16, String) + Send + Sync + 'static,
+) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let respond = Arc::new(respond);
+
+    let log = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let (log, respond) = (log.clone(), respond.clone());
+            tokio::spawn(async move {
+                // Requests are GETs, so the head is all there is.
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => head.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&head);
+                let target = head.split(' ').nth(1).unwrap_or("/");
+                let url = Url::parse(&format!("http://{}{}", addr, target)).unwrap();
+                let request = Request {
+                    query: url.query_pairs().into_owned().collect(),
+                };
+
+                let (status, body) = respond(&request);
+                log.lock().unwrap().push(request);
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    (format!("http://{}", addr), requests)
+}
+
+/// `serve`, with a client that talks to it without rate limits or retries.
+pub(crate) async fn hiscores(
+    respond: impl Fn(&Request) -> (u16, String) + Send + Sync + 'static,
+) -> (OsrsClient, Arc<Mutex<Vec<Request>>>) {
+    let (base_url, requests) = serve(respond).await;
+    let osrs = OsrsClient::builder()
+        .base_url(base_url)
+        .requests_per_second(1000.0)
+        .burst(1000)
+        .retry(RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        })
+        .build()
+        .unwrap();
+    (osrs, requests)
+}
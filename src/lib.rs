@@ -0,0 +1,8 @@
+//! Polls the Old School RuneScape hiscores into MongoDB, SQLite or Postgres.
+
+pub mod config;
+pub mod db;
+#[cfg(test)]
+mod fixtures;
+pub mod osrs;
+pub mod poll;
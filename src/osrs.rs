@@ -1,224 +1,470 @@
-use reqwest::StatusCode;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct HiscoresUser {
-    pub name: String,
-    pub score: u32,
+pub use client::OsrsClient;
+pub use error::OsrsError;
+use layout::{HiscoreLayout, LayoutError};
+
+pub mod client;
+mod error;
+pub mod layout;
+pub mod rate_limit;
+
+/// The hiscores a player can appear on. Each mode is served from its own
+/// `m=hiscore_oldschool*` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum GameMode {
+    Regular,
+    Ironman,
+    HardcoreIronman,
+    UltimateIronman,
+    Deadman,
+    Seasonal,
+    Tournament,
+    FreshStart,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 8] = [
+        GameMode::Regular,
+        GameMode::Ironman,
+        GameMode::HardcoreIronman,
+        GameMode::UltimateIronman,
+        GameMode::Deadman,
+        GameMode::Seasonal,
+        GameMode::Tournament,
+        GameMode::FreshStart,
+    ];
+
+    /// Name used in the database and in configuration.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameMode::Regular => "regular",
+            GameMode::Ironman => "ironman",
+            GameMode::HardcoreIronman => "hardcoreIronman",
+            GameMode::UltimateIronman => "ultimateIronman",
+            GameMode::Deadman => "deadman",
+            GameMode::Seasonal => "seasonal",
+            GameMode::Tournament => "tournament",
+            GameMode::FreshStart => "freshStart",
+        }
+    }
+
+    fn endpoint(self) -> &'static str {
+        match self {
+            GameMode::Regular => "hiscore_oldschool",
+            GameMode::Ironman => "hiscore_oldschool_ironman",
+            GameMode::HardcoreIronman => "hiscore_oldschool_hardcore_ironman",
+            GameMode::UltimateIronman => "hiscore_oldschool_ultimate",
+            GameMode::Deadman => "hiscore_oldschool_deadman",
+            GameMode::Seasonal => "hiscore_oldschool_seasonal",
+            GameMode::Tournament => "hiscore_oldschool_tournament",
+            GameMode::FreshStart => "hiscore_oldschool_fresh_start",
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GameMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown game mode {}", s))
+    }
+}
+
+/// The two kinds of hiscore tables, as the `category_type` parameter.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+    Skill,
+    Activity,
+}
+
+impl Category {
+    fn id(self) -> u8 {
+        match self {
+            Category::Skill => 0,
+            Category::Activity => 1,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Skill => "skill",
+            Category::Activity => "activity",
+        }
+    }
+}
+
+/// A single hiscores table, e.g. a skill or a boss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Leaderboard {
+    pub category: Category,
+    /// Position of the skill or activity within its category.
+    pub table: u32,
+}
+
+impl Leaderboard {
+    /// The seasonal league points table.
+    pub const LEAGUE_POINTS: Leaderboard = Leaderboard {
+        category: Category::Activity,
+        table: 0,
+    };
+
+    /// Overall, the first skill table.
+    pub const OVERALL: Leaderboard = Leaderboard {
+        category: Category::Skill,
+        table: 0,
+    };
+
+    /// The table a game mode is ranked by: league points for leagues,
+    /// overall for every other mode.
+    pub fn main(game_mode: GameMode) -> Leaderboard {
+        match game_mode {
+            GameMode::Seasonal => Leaderboard::LEAGUE_POINTS,
+            _ => Leaderboard::OVERALL,
+        }
+    }
+}
+
+/// Formats as `category:table`, e.g. `activity:0`, which is also how the SQL
+/// stores keep leaderboards.
+impl fmt::Display for Leaderboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.category.as_str(), self.table)
+    }
+}
+
+/// Parses `skill:<table>` or `activity:<table>`.
+impl FromStr for Leaderboard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (category, table) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected category:table, got {}", s))?;
+        let category = match category {
+            "skill" => Category::Skill,
+            "activity" => Category::Activity,
+            _ => return Err(format!("unknown hiscores category {}", category)),
+        };
+        let table = table
+            .parse()
+            .map_err(|_| format!("bad hiscores table {}", table))?;
+        Ok(Leaderboard { category, table })
+    }
+}
+
+/// A row of a leaderboard page, shaped by the table's category.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum HiscoresRow {
+    Skill {
+        rank: u64,
+        name: String,
+        level: u64,
+        xp: u64,
+    },
+    Activity {
+        rank: u64,
+        name: String,
+        score: u64,
+    },
+}
+
+impl HiscoresRow {
+    pub fn rank(&self) -> u64 {
+        match self {
+            HiscoresRow::Skill { rank, .. } | HiscoresRow::Activity { rank, .. } => *rank,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            HiscoresRow::Skill { name, .. } | HiscoresRow::Activity { name, .. } => name,
+        }
+    }
+
+    /// The column the table is ordered by: xp for skills, score otherwise.
+    pub fn score(&self) -> u64 {
+        match self {
+            HiscoresRow::Skill { xp, .. } => *xp,
+            HiscoresRow::Activity { score, .. } => *score,
+        }
+    }
+
+    pub fn level(&self) -> Option<u64> {
+        match self {
+            HiscoresRow::Skill { level, .. } => Some(*level),
+            HiscoresRow::Activity { .. } => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct HiscoresIndex {
-    pub users: Vec<HiscoresUser>,
+    pub rows: Vec<HiscoresRow>,
+    /// No page follows this one.
+    pub is_last: bool,
+}
+
+fn parse_selector(selector: &str) -> Result<Selector, OsrsError> {
+    Selector::parse(selector)
+        .map_err(|_| OsrsError::HtmlLayout(format!("bad selector {}", selector)))
+}
+
+fn parse_number(cell: &str) -> Result<u64, OsrsError> {
+    let number = cell.trim().replace(',', "");
+    number
+        .parse()
+        .map_err(|_| OsrsError::HtmlLayout(format!("bad number {:?}", number)))
+}
+
+/// Which hiscores endpoint `user_hiscore` reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HiscoreBackend {
+    /// `index_lite.ws`, parsed by position.
+    Lite,
+    /// `index_lite.json`, parsed by name.
+    Json,
+    /// Fetch both, report any disagreement and keep the JSON result.
+    CrossCheck,
 }
 
-pub async fn hiscores_index(
-    page: usize,
-) -> Result<Option<HiscoresIndex>, Box<dyn std::error::Error + Send + Sync + 'static>> {
-    let selector =
-        Selector::parse("tr.personal-hiscores__row").map_err(|_| "failed selector parse")?;
-    let href_selector = Selector::parse("a").map_err(|_| "failed selector parse")?;
-    let score_selector = Selector::parse("td.right").map_err(|_| "failed selector parse")?;
-    let next_selector = Selector::parse("a.personal-hiscores__pagination-arrow--down")
-        .map_err(|_| "failed selector parse")?;
-
-    let mut users: Vec<HiscoresUser> = Vec::new();
-
-    let url = format!("https://secure.runescape.com/m=hiscore_oldschool_seasonal/overall?category_type=1&table=0&page={}", page);
-    let response = reqwest::get(url).await?;
-    let document = Html::parse_document(&response.text().await?);
-
-    if let Some(next) = document.select(&next_selector).next() {
-        let mut href = next
-            .value()
-            .attr("href")
-            .ok_or("Can't get href")?
-            .chars()
-            .collect::<Vec<_>>();
-        href.drain(0..37);
-        let next_page: usize = href.into_iter().collect::<String>().parse()?;
-        if next_page <= page {
-            return Ok(None);
+impl FromStr for HiscoreBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lite" => Ok(HiscoreBackend::Lite),
+            "json" => Ok(HiscoreBackend::Json),
+            "crossCheck" => Ok(HiscoreBackend::CrossCheck),
+            _ => Err(format!("unknown hiscore backend {}", s)),
         }
-    } else {
-        return Ok(None);
-    }
-
-    for element in document.select(&selector) {
-        if let Some(user) = element.select(&href_selector).next() {
-            let mut scores = element.select(&score_selector);
-            let _ = scores.next();
-            if let Some(score) = scores.next() {
-                users.push(HiscoresUser {
-                    name: user.text().collect::<String>().replace('\u{A0}', " "),
-                    score: score
-                        .text()
-                        .collect::<String>()
-                        .trim()
-                        .replace(",", "")
-                        .parse()?,
+    }
+}
+
+impl OsrsClient {
+    pub async fn hiscores_index(
+        &self,
+        mode: GameMode,
+        leaderboard: Leaderboard,
+        page: usize,
+    ) -> Result<HiscoresIndex, OsrsError> {
+        let request = self.get(mode, "overall").query(&[
+            ("category_type", &leaderboard.category.id().to_string()),
+            ("table", &leaderboard.table.to_string()),
+            ("page", &page.to_string()),
+        ]);
+        let body = self.send(request).await?;
+        self.parse_hiscores_index(mode, leaderboard, page, &body)
+    }
+
+    /// Reads the rows of a leaderboard page and whether another page follows.
+    fn parse_hiscores_index(
+        &self,
+        mode: GameMode,
+        leaderboard: Leaderboard,
+        page: usize,
+        body: &str,
+    ) -> Result<HiscoresIndex, OsrsError> {
+        let selector = parse_selector("tr.personal-hiscores__row")?;
+        let href_selector = parse_selector("a")?;
+        let number_selector = parse_selector("td.right")?;
+        let next_selector = parse_selector("a.personal-hiscores__pagination-arrow--down")?;
+
+        let mut rows: Vec<HiscoresRow> = Vec::new();
+        let document = Html::parse_document(body);
+
+        let is_last = match document.select(&next_selector).next() {
+            Some(next) => {
+                let href = next.value().attr("href").ok_or_else(|| {
+                    OsrsError::HtmlLayout("pagination arrow has no href".to_string())
+                })?;
+                let next_page = self.resolve(mode, href).ok().and_then(|url| {
+                    url.query_pairs()
+                        .find(|(key, _)| key == "page")
+                        .and_then(|(_, value)| value.parse::<usize>().ok())
                 });
+                match next_page {
+                    // Past the end the arrow points back at the current page.
+                    Some(next_page) => next_page <= page,
+                    None => {
+                        return Err(OsrsError::HtmlLayout(format!(
+                            "no page in pagination link {:?}",
+                            href
+                        )))
+                    }
+                }
+            }
+            None => true,
+        };
+
+        for element in document.select(&selector) {
+            let Some(user) = element.select(&href_selector).next() else {
+                continue;
+            };
+            let name = user.text().collect::<String>().replace('\u{A0}', " ");
+            let numbers = element
+                .select(&number_selector)
+                .map(|cell| parse_number(&cell.text().collect::<String>()))
+                .collect::<Result<Vec<u64>, OsrsError>>()?;
+            let row = match (leaderboard.category, numbers.as_slice()) {
+                (Category::Skill, &[rank, level, xp]) => HiscoresRow::Skill {
+                    rank,
+                    name,
+                    level,
+                    xp,
+                },
+                (Category::Activity, &[rank, score]) => HiscoresRow::Activity { rank, name, score },
+                _ => {
+                    return Err(OsrsError::HtmlLayout(format!(
+                        "expected rank and {} columns for {}, found {:?}",
+                        leaderboard.category.as_str(),
+                        name,
+                        numbers
+                    )))
+                }
+            };
+            rows.push(row);
+        }
+
+        Ok(HiscoresIndex { rows, is_last })
+    }
+
+    pub async fn user_hiscore(
+        &self,
+        backend: HiscoreBackend,
+        mode: GameMode,
+        user: String,
+    ) -> Result<Hiscore, OsrsError> {
+        let layout = HiscoreLayout::current();
+        match backend {
+            HiscoreBackend::Lite => {
+                let body = self.fetch_user(mode, &user, "index_lite.ws").await?;
+                Ok(layout.parse(&body)?)
+            }
+            HiscoreBackend::Json => {
+                let body = self.fetch_user(mode, &user, "index_lite.json").await?;
+                Ok(layout.parse_json(&body)?)
+            }
+            HiscoreBackend::CrossCheck => {
+                let json = self.fetch_user(mode, &user, "index_lite.json").await?;
+                let json = layout.parse_json(&json)?;
+                let lite = self
+                    .fetch_user(mode, &user, "index_lite.ws")
+                    .await
+                    .and_then(|lite| Ok(layout.parse(&lite)?));
+                match lite {
+                    Ok(lite) => {
+                        for disagreement in compare_hiscores(&lite, &json) {
+                            println!("Hiscores disagree for {}: {}", user, disagreement);
+                        }
+                    }
+                    Err(err) => println!("Lite hiscores failed for {}: {}", user, err),
+                }
+                Ok(json)
             }
         }
     }
 
-    Ok(Some(HiscoresIndex { users }))
+    async fn fetch_user(
+        &self,
+        mode: GameMode,
+        user: &str,
+        file: &str,
+    ) -> Result<String, OsrsError> {
+        self.send(self.get(mode, file).query(&[("player", user)]))
+            .await
+    }
 }
 
-pub async fn user_hiscore(
-    user: String,
-) -> Result<Option<Hiscore>, Box<dyn std::error::Error + Send + Sync + 'static>> {
-    println!("About to make request...");
-    let url = format!(
-        "https://secure.runescape.com/m=hiscore_oldschool_seasonal/index_lite.ws?player={}",
-        user
-    );
-    let res = reqwest::get(url).await?;
-    if res.status() != StatusCode::OK {
-        return Ok(None);
-    }
-    println!("HERE");
-    let response = res.text().await?;
-    let entries: Vec<&str> = response.split('\n').collect();
-    println!("{:?}", entries);
-    Ok(Some(Hiscore {
-        skills: HiscoreSkills {
-            overall: extract_skill_entry(entries[0])?,
-            attack: extract_skill_entry(entries[1])?,
-            defence: extract_skill_entry(entries[2])?,
-            strength: extract_skill_entry(entries[3])?,
-            hitpoints: extract_skill_entry(entries[4])?,
-            ranged: extract_skill_entry(entries[5])?,
-            prayer: extract_skill_entry(entries[6])?,
-            magic: extract_skill_entry(entries[7])?,
-            cooking: extract_skill_entry(entries[8])?,
-            woodcutting: extract_skill_entry(entries[9])?,
-            fletching: extract_skill_entry(entries[10])?,
-            fishing: extract_skill_entry(entries[11])?,
-            firemaking: extract_skill_entry(entries[12])?,
-            crafting: extract_skill_entry(entries[13])?,
-            smithing: extract_skill_entry(entries[14])?,
-            mining: extract_skill_entry(entries[15])?,
-            herblore: extract_skill_entry(entries[16])?,
-            agility: extract_skill_entry(entries[17])?,
-            thieving: extract_skill_entry(entries[18])?,
-            slayer: extract_skill_entry(entries[19])?,
-            farming: extract_skill_entry(entries[20])?,
-            runecraft: extract_skill_entry(entries[21])?,
-            hunter: extract_skill_entry(entries[22])?,
-            construction: extract_skill_entry(entries[23])?,
-        },
-        activities: HiscoreActivities {
-            league_points: extract_activity_entry(entries[24]),
-            //    deadman_points: extract_activity_entry(entries[25]),
-            //    bounty_hunter_-_hunter: extract_activity_entry(entries[26]),
-            //    bounty_hunter_-_rogue: extract_activity_entry(entries[27]),
-            //    bounty_hunter_(legacy)_-_hunter: extract_activity_entry(entries[28]),
-            //    bounty_hunter_(legacy)_-_rogue: extract_activity_entry(entries[29]),
-            clue_scrolls_all: extract_activity_entry(entries[30]),
-            clue_scrolls_beginner: extract_activity_entry(entries[31]),
-            clue_scrolls_easy: extract_activity_entry(entries[32]),
-            clue_scrolls_medium: extract_activity_entry(entries[33]),
-            clue_scrolls_hard: extract_activity_entry(entries[34]),
-            clue_scrolls_elite: extract_activity_entry(entries[35]),
-            clue_scrolls_master: extract_activity_entry(entries[36]),
-            //    lms_-_rank: extract_activity_entry(entries[37]),
-            //    pvp_arena_-_rank: extract_activity_entry(entries[38]),
-            soul_wars_zeal: extract_activity_entry(entries[39]),
-            rifts_closed: extract_activity_entry(entries[40]),
-            abyssal_sire: extract_activity_entry(entries[41]),
-            alchemical_hydra: extract_activity_entry(entries[42]),
-            artio: extract_activity_entry(entries[43]),
-            barrows_chests: extract_activity_entry(entries[44]),
-            bryophyta: extract_activity_entry(entries[45]),
-            callisto: extract_activity_entry(entries[46]),
-            calvarion: extract_activity_entry(entries[47]),
-            cerberus: extract_activity_entry(entries[48]),
-            chambers_of_xeric: extract_activity_entry(entries[49]),
-            chambers_of_xeric_challenge_mode: extract_activity_entry(entries[50]),
-            chaos_elemental: extract_activity_entry(entries[51]),
-            chaos_fanatic: extract_activity_entry(entries[52]),
-            commander_zilyana: extract_activity_entry(entries[53]),
-            corporeal_beast: extract_activity_entry(entries[54]),
-            crazy_archaeologist: extract_activity_entry(entries[55]),
-            dagannoth_prime: extract_activity_entry(entries[56]),
-            dagannoth_rex: extract_activity_entry(entries[57]),
-            dagannoth_supreme: extract_activity_entry(entries[58]),
-            deranged_archaeologist: extract_activity_entry(entries[59]),
-            duke_sucellus: extract_activity_entry(entries[60]),
-            general_graardor: extract_activity_entry(entries[61]),
-            giant_mole: extract_activity_entry(entries[62]),
-            grotesque_guardians: extract_activity_entry(entries[63]),
-            hespori: extract_activity_entry(entries[64]),
-            kalphite_queen: extract_activity_entry(entries[65]),
-            king_black_dragon: extract_activity_entry(entries[66]),
-            kraken: extract_activity_entry(entries[67]),
-            kreearra: extract_activity_entry(entries[68]),
-            kril_tsutsaroth: extract_activity_entry(entries[69]),
-            mimic: extract_activity_entry(entries[70]),
-            nex: extract_activity_entry(entries[71]),
-            nightmare: extract_activity_entry(entries[72]),
-            phosanis_nightmare: extract_activity_entry(entries[73]),
-            obor: extract_activity_entry(entries[74]),
-            phantom_muspah: extract_activity_entry(entries[75]),
-            sarachnis: extract_activity_entry(entries[76]),
-            scorpia: extract_activity_entry(entries[77]),
-            skotizo: extract_activity_entry(entries[78]),
-            spindel: extract_activity_entry(entries[79]),
-            tempoross: extract_activity_entry(entries[80]),
-            the_gauntlet: extract_activity_entry(entries[81]),
-            the_corrupted_gauntlet: extract_activity_entry(entries[82]),
-            the_leviathan: extract_activity_entry(entries[83]),
-            the_whisperer: extract_activity_entry(entries[84]),
-            theatre_of_blood: extract_activity_entry(entries[85]),
-            theatre_of_blood_hard_mode: extract_activity_entry(entries[86]),
-            thermonuclear_smoke_devil: extract_activity_entry(entries[87]),
-            tombs_of_amascut: extract_activity_entry(entries[88]),
-            tombs_of_amascut_expert_mode: extract_activity_entry(entries[89]),
-            tzkal_zuk: extract_activity_entry(entries[90]),
-            tztok_jad: extract_activity_entry(entries[91]),
-            vardorvis: extract_activity_entry(entries[92]),
-            venenatis: extract_activity_entry(entries[93]),
-            vetion: extract_activity_entry(entries[94]),
-            vorkath: extract_activity_entry(entries[95]),
-            wintertodt: extract_activity_entry(entries[96]),
-            zalcano: extract_activity_entry(entries[97]),
-            zulrah: extract_activity_entry(entries[98]),
-        },
-    }))
+/// A skill or activity the two hiscore parsers read differently.
+#[derive(Debug)]
+pub struct Disagreement {
+    pub key: String,
+    pub lite: serde_json::Value,
+    pub json: serde_json::Value,
 }
 
-fn extract_skill_entry(
-    entry: &str,
-) -> Result<HiscoreSkillEntry, Box<dyn std::error::Error + Send + Sync + 'static>> {
-    let entries: Vec<u32> = entry
-        .split(',')
-        .map(|s| s.parse::<u32>().map_err(|_| "bad u32"))
-        .collect::<Result<Vec<u32>, &str>>()?;
-    Ok(HiscoreSkillEntry {
-        rank: *entries.get(0).ok_or("err no 0 index")?,
-        level: *entries.get(1).ok_or("err no 1 index")?,
-        xp: *entries.get(2).ok_or("err no 2 index")?,
-    })
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: lite {} json {}", self.key, self.lite, self.json)
+    }
 }
 
-fn extract_activity_entry(entry: &str) -> Option<HiscoreActivityEntry> {
-    let entries: Vec<u32> = entry
-        .split(',')
-        .map(|s| s.parse::<u32>().ok())
-        .collect::<Option<Vec<u32>>>()?;
-    Some(HiscoreActivityEntry {
-        rank: *entries.get(0)?,
-        score: *entries.get(1)?,
-    })
+pub fn compare_hiscores(lite: &Hiscore, json: &Hiscore) -> Vec<Disagreement> {
+    let (Ok(lite), Ok(json)) = (serde_json::to_value(lite), serde_json::to_value(json)) else {
+        return Vec::new();
+    };
+
+    let mut disagreements = Vec::new();
+    for section in ["skills", "activities"] {
+        let empty = serde_json::Map::new();
+        let lite = lite[section].as_object().unwrap_or(&empty);
+        let json = json[section].as_object().unwrap_or(&empty);
+        let keys: BTreeSet<&String> = lite.keys().chain(json.keys()).collect();
+        for key in keys {
+            let lite = lite.get(key).cloned().unwrap_or_default();
+            let json = json.get(key).cloned().unwrap_or_default();
+            if lite != json {
+                disagreements.push(Disagreement {
+                    key: format!("{}.{}", section, key),
+                    lite,
+                    json,
+                });
+            }
+        }
+    }
+    disagreements
 }
 
+/// A skill on the hiscores. Skills a player is unranked in are stored without
+/// a rank, with `xp` and `level` as the best known lower bound.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-pub struct HiscoreSkillEntry {
-    xp: u32,
-    level: u32,
-    rank: u32,
+#[serde(untagged)]
+pub enum HiscoreSkillEntry {
+    Ranked { xp: u64, level: u64, rank: u64 },
+    Unranked { xp: u64, level: u64 },
+}
+
+impl HiscoreSkillEntry {
+    pub fn xp(&self) -> u64 {
+        match self {
+            HiscoreSkillEntry::Ranked { xp, .. } | HiscoreSkillEntry::Unranked { xp, .. } => *xp,
+        }
+    }
+
+    pub fn level(&self) -> u64 {
+        match self {
+            HiscoreSkillEntry::Ranked { level, .. } | HiscoreSkillEntry::Unranked { level, .. } => {
+                *level
+            }
+        }
+    }
+
+    pub fn rank(&self) -> Option<u64> {
+        match self {
+            HiscoreSkillEntry::Ranked { rank, .. } => Some(*rank),
+            HiscoreSkillEntry::Unranked { .. } => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -249,90 +495,72 @@ pub struct HiscoreSkills {
     construction: HiscoreSkillEntry,
 }
 
+impl HiscoreSkills {
+    /// The skill named `key` in the hiscore layout, e.g. `"attack"`.
+    pub fn get(&self, key: &str) -> Option<&HiscoreSkillEntry> {
+        Some(match key {
+            "overall" => &self.overall,
+            "attack" => &self.attack,
+            "defence" => &self.defence,
+            "strength" => &self.strength,
+            "hitpoints" => &self.hitpoints,
+            "ranged" => &self.ranged,
+            "prayer" => &self.prayer,
+            "magic" => &self.magic,
+            "cooking" => &self.cooking,
+            "woodcutting" => &self.woodcutting,
+            "fletching" => &self.fletching,
+            "fishing" => &self.fishing,
+            "firemaking" => &self.firemaking,
+            "crafting" => &self.crafting,
+            "smithing" => &self.smithing,
+            "mining" => &self.mining,
+            "herblore" => &self.herblore,
+            "agility" => &self.agility,
+            "thieving" => &self.thieving,
+            "slayer" => &self.slayer,
+            "farming" => &self.farming,
+            "runecraft" => &self.runecraft,
+            "hunter" => &self.hunter,
+            "construction" => &self.construction,
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct HiscoreActivityEntry {
-    score: u32,
-    rank: u32,
+    score: u64,
+    rank: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
-pub struct HiscoreActivities {
-    league_points: Option<HiscoreActivityEntry>,
-    //    deadman_points: Option<HiscoreActivityEntry>,
-    //    bounty_hunter_-_hunter: Option<HiscoreActivityEntry>,
-    //    bounty_hunter_-_rogue: Option<HiscoreActivityEntry>,
-    //    bounty_hunter_(legacy)_-_hunter: Option<HiscoreActivityEntry>,
-    //    bounty_hunter_(legacy)_-_rogue: Option<HiscoreActivityEntry>,
-    clue_scrolls_all: Option<HiscoreActivityEntry>,
-    clue_scrolls_beginner: Option<HiscoreActivityEntry>,
-    clue_scrolls_easy: Option<HiscoreActivityEntry>,
-    clue_scrolls_medium: Option<HiscoreActivityEntry>,
-    clue_scrolls_hard: Option<HiscoreActivityEntry>,
-    clue_scrolls_elite: Option<HiscoreActivityEntry>,
-    clue_scrolls_master: Option<HiscoreActivityEntry>,
-    //    lms_-_rank: Option<HiscoreActivityEntry>,
-    //    pvp_arena_-_rank: Option<HiscoreActivityEntry>,
-    soul_wars_zeal: Option<HiscoreActivityEntry>,
-    rifts_closed: Option<HiscoreActivityEntry>,
-    abyssal_sire: Option<HiscoreActivityEntry>,
-    alchemical_hydra: Option<HiscoreActivityEntry>,
-    artio: Option<HiscoreActivityEntry>,
-    barrows_chests: Option<HiscoreActivityEntry>,
-    bryophyta: Option<HiscoreActivityEntry>,
-    callisto: Option<HiscoreActivityEntry>,
-    calvarion: Option<HiscoreActivityEntry>,
-    cerberus: Option<HiscoreActivityEntry>,
-    chambers_of_xeric: Option<HiscoreActivityEntry>,
-    chambers_of_xeric_challenge_mode: Option<HiscoreActivityEntry>,
-    chaos_elemental: Option<HiscoreActivityEntry>,
-    chaos_fanatic: Option<HiscoreActivityEntry>,
-    commander_zilyana: Option<HiscoreActivityEntry>,
-    corporeal_beast: Option<HiscoreActivityEntry>,
-    crazy_archaeologist: Option<HiscoreActivityEntry>,
-    dagannoth_prime: Option<HiscoreActivityEntry>,
-    dagannoth_rex: Option<HiscoreActivityEntry>,
-    dagannoth_supreme: Option<HiscoreActivityEntry>,
-    deranged_archaeologist: Option<HiscoreActivityEntry>,
-    duke_sucellus: Option<HiscoreActivityEntry>,
-    general_graardor: Option<HiscoreActivityEntry>,
-    giant_mole: Option<HiscoreActivityEntry>,
-    grotesque_guardians: Option<HiscoreActivityEntry>,
-    hespori: Option<HiscoreActivityEntry>,
-    kalphite_queen: Option<HiscoreActivityEntry>,
-    king_black_dragon: Option<HiscoreActivityEntry>,
-    kraken: Option<HiscoreActivityEntry>,
-    kreearra: Option<HiscoreActivityEntry>,
-    kril_tsutsaroth: Option<HiscoreActivityEntry>,
-    mimic: Option<HiscoreActivityEntry>,
-    nex: Option<HiscoreActivityEntry>,
-    nightmare: Option<HiscoreActivityEntry>,
-    phosanis_nightmare: Option<HiscoreActivityEntry>,
-    obor: Option<HiscoreActivityEntry>,
-    phantom_muspah: Option<HiscoreActivityEntry>,
-    sarachnis: Option<HiscoreActivityEntry>,
-    scorpia: Option<HiscoreActivityEntry>,
-    skotizo: Option<HiscoreActivityEntry>,
-    spindel: Option<HiscoreActivityEntry>,
-    tempoross: Option<HiscoreActivityEntry>,
-    the_gauntlet: Option<HiscoreActivityEntry>,
-    the_corrupted_gauntlet: Option<HiscoreActivityEntry>,
-    the_leviathan: Option<HiscoreActivityEntry>,
-    the_whisperer: Option<HiscoreActivityEntry>,
-    theatre_of_blood: Option<HiscoreActivityEntry>,
-    theatre_of_blood_hard_mode: Option<HiscoreActivityEntry>,
-    thermonuclear_smoke_devil: Option<HiscoreActivityEntry>,
-    tombs_of_amascut: Option<HiscoreActivityEntry>,
-    tombs_of_amascut_expert_mode: Option<HiscoreActivityEntry>,
-    tzkal_zuk: Option<HiscoreActivityEntry>,
-    tztok_jad: Option<HiscoreActivityEntry>,
-    vardorvis: Option<HiscoreActivityEntry>,
-    venenatis: Option<HiscoreActivityEntry>,
-    vetion: Option<HiscoreActivityEntry>,
-    vorkath: Option<HiscoreActivityEntry>,
-    wintertodt: Option<HiscoreActivityEntry>,
-    zalcano: Option<HiscoreActivityEntry>,
-    zulrah: Option<HiscoreActivityEntry>,
+impl HiscoreActivityEntry {
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    pub fn rank(&self) -> u64 {
+        self.rank
+    }
+}
+
+/// Activity scores keyed by the names in the hiscore layout. Activities a
+/// player is unranked in are stored as `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct HiscoreActivities(BTreeMap<String, Option<HiscoreActivityEntry>>);
+
+impl HiscoreActivities {
+    pub fn get(&self, key: &str) -> Option<&HiscoreActivityEntry> {
+        self.0.get(key).and_then(Option::as_ref)
+    }
+
+    /// Every activity by name, `None` where the player is unranked.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&HiscoreActivityEntry>)> {
+        self.0
+            .iter()
+            .map(|(key, entry)| (key.as_str(), entry.as_ref()))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -340,3 +568,124 @@ pub struct Hiscore {
     skills: HiscoreSkills,
     activities: HiscoreActivities,
 }
+
+impl Hiscore {
+    pub fn skills(&self) -> &HiscoreSkills {
+        &self.skills
+    }
+
+    pub fn activities(&self) -> &HiscoreActivities {
+        &self.activities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{leaderboard_page, skill_page};
+
+    const LEAGUE_POINTS: Leaderboard = Leaderboard {
+        category: Category::Activity,
+        table: 0,
+    };
+
+    const ROWS: &[(u64, &str, u64)] = &[(51, "Some Player", 12_345), (52, "Other", 12_000)];
+
+    fn parse(page: usize, body: &str) -> Result<HiscoresIndex, OsrsError> {
+        parse_as(LEAGUE_POINTS, page, body)
+    }
+
+    fn parse_as(
+        leaderboard: Leaderboard,
+        page: usize,
+        body: &str,
+    ) -> Result<HiscoresIndex, OsrsError> {
+        let client = OsrsClient::builder().build().unwrap();
+        client.parse_hiscores_index(GameMode::Seasonal, leaderboard, page, body)
+    }
+
+    #[test]
+    fn reads_rows() {
+        let index = parse(3, &leaderboard_page(ROWS, None)).unwrap();
+        let rows: Vec<(u64, &str, u64)> = index
+            .rows
+            .iter()
+            .map(|row| (row.rank(), row.name(), row.score()))
+            .collect();
+        assert_eq!(rows, [(51, "Some Player", 12_345), (52, "Other", 12_000)]);
+    }
+
+    #[test]
+    fn relative_arrow_to_a_later_page_is_not_last() {
+        let body = leaderboard_page(ROWS, Some("overall?category_type=1&table=0&page=4"));
+        assert!(!parse(3, &body).unwrap().is_last);
+    }
+
+    #[test]
+    fn absolute_arrow_to_a_later_page_is_not_last() {
+        let body = leaderboard_page(ROWS, Some(
+            "https://secure.runescape.com/m=hiscore_oldschool_seasonal/overall?category_type=1&table=0&page=4",
+        ));
+        assert!(!parse(3, &body).unwrap().is_last);
+    }
+
+    #[test]
+    fn arrow_back_to_the_current_page_is_last() {
+        let body = leaderboard_page(ROWS, Some("overall?category_type=1&table=0&page=3"));
+        assert!(parse(3, &body).unwrap().is_last);
+    }
+
+    #[test]
+    fn missing_arrow_is_last() {
+        assert!(parse(3, &leaderboard_page(ROWS, None)).unwrap().is_last);
+    }
+
+    #[test]
+    fn arrow_without_a_page_is_an_error() {
+        let body = leaderboard_page(ROWS, Some("overall?category_type=1&table=0"));
+        assert!(matches!(parse(3, &body), Err(OsrsError::HtmlLayout(_))));
+    }
+
+    #[test]
+    fn wrong_columns_are_an_error() {
+        let body = leaderboard_page(ROWS, None).replace(r#"<td class="right">12,000</td>"#, "");
+        assert!(matches!(parse(3, &body), Err(OsrsError::HtmlLayout(_))));
+    }
+
+    #[test]
+    fn reads_skill_rows() {
+        let body = skill_page(&[(1, "Lynx Titan", 2_277, 4_600_000_000)], None);
+        let index = parse_as(Leaderboard::OVERALL, 1, &body).unwrap();
+        let row = &index.rows[0];
+        assert_eq!((row.rank(), row.name()), (1, "Lynx Titan"));
+        assert_eq!((row.level(), row.score()), (Some(2_277), 4_600_000_000));
+    }
+
+    #[test]
+    fn skill_rows_without_level_and_xp_are_an_error() {
+        let body = skill_page(&[(1, "Lynx Titan", 2_277, 4_600_000_000)], None)
+            .replace(r#"<td class="right">2,277</td>"#, "");
+        assert!(matches!(
+            parse_as(Leaderboard::OVERALL, 1, &body),
+            Err(OsrsError::HtmlLayout(_))
+        ));
+        // An activity page has one column too few for a skill table.
+        assert!(matches!(
+            parse_as(Leaderboard::OVERALL, 1, &leaderboard_page(ROWS, None)),
+            Err(OsrsError::HtmlLayout(_))
+        ));
+    }
+
+    #[test]
+    fn leagues_are_ranked_by_points_and_other_modes_by_overall() {
+        assert_eq!(
+            Leaderboard::main(GameMode::Seasonal),
+            Leaderboard::LEAGUE_POINTS
+        );
+        for game_mode in GameMode::ALL {
+            if game_mode != GameMode::Seasonal {
+                assert_eq!(Leaderboard::main(game_mode), Leaderboard::OVERALL);
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::{env, error::Error, fmt, sync::Arc, time::Duration};
+
+use reqwest::{Proxy, RequestBuilder, Url};
+
+use super::{
+    rate_limit::{RateLimiter, RetryPolicy},
+    GameMode, OsrsError,
+};
+
+const DEFAULT_BASE_URL: &str = "https://secure.runescape.com";
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Connection to the hiscores shared by every request a process makes.
+/// Cloning is cheap and reuses the same connection pool and rate limiter.
+#[derive(Clone)]
+pub struct OsrsClient {
+    http: reqwest::Client,
+    base_url: String,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+}
+
+impl OsrsClient {
+    pub fn builder() -> OsrsClientBuilder {
+        OsrsClientBuilder::default()
+    }
+
+    /// Builds a client from `OSRS_BASE_URL`, `OSRS_TIMEOUT_SECS`,
+    /// `OSRS_CONNECT_TIMEOUT_SECS`, `OSRS_USER_AGENT`, `OSRS_PROXY`,
+    /// `OSRS_REQUESTS_PER_SECOND`, `OSRS_BURST`, `OSRS_MAX_RETRIES`,
+    /// `OSRS_RETRY_BASE_MS` and `OSRS_RETRY_MAX_MS`, using the defaults for
+    /// any that are unset.
+    pub fn from_env() -> Result<OsrsClient, Box<dyn std::error::Error + Send + Sync>> {
+        let mut builder = OsrsClient::builder();
+        if let Ok(base_url) = env::var("OSRS_BASE_URL") {
+            builder = builder.base_url(base_url);
+        }
+        if let Ok(timeout) = env::var("OSRS_TIMEOUT_SECS") {
+            builder = builder.timeout(Duration::from_secs(timeout.parse()?));
+        }
+        if let Ok(timeout) = env::var("OSRS_CONNECT_TIMEOUT_SECS") {
+            builder = builder.connect_timeout(Duration::from_secs(timeout.parse()?));
+        }
+        if let Ok(user_agent) = env::var("OSRS_USER_AGENT") {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Ok(proxy) = env::var("OSRS_PROXY") {
+            builder = builder.proxy(proxy);
+        }
+        if let Ok(per_second) = env::var("OSRS_REQUESTS_PER_SECOND") {
+            builder = builder.requests_per_second(per_second.parse()?);
+        }
+        if let Ok(burst) = env::var("OSRS_BURST") {
+            builder = builder.burst(burst.parse()?);
+        }
+        let mut retry = RetryPolicy::default();
+        if let Ok(max_retries) = env::var("OSRS_MAX_RETRIES") {
+            retry.max_retries = max_retries.parse()?;
+        }
+        if let Ok(base_delay) = env::var("OSRS_RETRY_BASE_MS") {
+            retry.base_delay = Duration::from_millis(base_delay.parse()?);
+        }
+        if let Ok(max_delay) = env::var("OSRS_RETRY_MAX_MS") {
+            retry.max_delay = Duration::from_millis(max_delay.parse()?);
+        }
+        Ok(builder.retry(retry).build()?)
+    }
+
+    /// Resolves a link found on one of `mode`'s hiscores pages.
+    pub(super) fn resolve(&self, mode: GameMode, href: &str) -> Result<Url, String> {
+        Url::parse(&format!("{}/m={}/", self.base_url, mode.endpoint()))
+            .and_then(|base| base.join(href))
+            .map_err(|err| err.to_string())
+    }
+
+    pub(super) fn get(&self, mode: GameMode, path: &str) -> RequestBuilder {
+        self.http
+            .get(format!("{}/m={}/{}", self.base_url, mode.endpoint(), path))
+    }
+
+    /// Sends `request` once the rate limiter allows it, retrying failures
+    /// that are likely to go away on their own.
+    pub(super) async fn send(&self, request: RequestBuilder) -> Result<String, OsrsError> {
+        let mut retry = 0;
+        loop {
+            let attempt = request
+                .try_clone()
+                .expect("hiscore requests have no streaming body");
+            self.limiter.acquire().await;
+            let err = match self.send_once(attempt).await {
+                Ok(body) => return Ok(body),
+                Err(err) => err,
+            };
+            if retry >= self.retry.max_retries || !err.is_retryable() {
+                return Err(err);
+            }
+
+            let retry_after = match err {
+                OsrsError::RateLimited { retry_after } => retry_after,
+                _ => None,
+            };
+            let delay = self.retry.delay(retry, retry_after);
+            println!("{}, retrying in {:?}", err, delay);
+            tokio::time::sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    async fn send_once(&self, request: RequestBuilder) -> Result<String, OsrsError> {
+        let res = request.send().await?;
+        if let Some(err) = OsrsError::from_response(&res) {
+            return Err(err);
+        }
+        Ok(res.text().await?)
+    }
+}
+
+pub struct OsrsClientBuilder {
+    base_url: String,
+    timeout: Duration,
+    connect_timeout: Duration,
+    user_agent: String,
+    proxy: Option<String>,
+    requests_per_second: f64,
+    burst: u32,
+    retry: RetryPolicy,
+}
+
+impl Default for OsrsClientBuilder {
+    fn default() -> Self {
+        OsrsClientBuilder {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            proxy: None,
+            requests_per_second: 2.0,
+            burst: 4,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl OsrsClientBuilder {
+    /// Where the `m=hiscore_oldschool*` endpoints live, e.g. a local mock
+    /// server.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Limit on a whole request, including reading the body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Proxy URL every request is sent through.
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Sustained request rate across every clone of the client. Must be
+    /// positive and finite.
+    pub fn requests_per_second(mut self, requests_per_second: f64) -> Self {
+        self.requests_per_second = requests_per_second;
+        self
+    }
+
+    /// Requests that may be sent back to back after a quiet period.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn build(self) -> Result<OsrsClient, BuildError> {
+        if !self.requests_per_second.is_finite() || self.requests_per_second <= 0.0 {
+            return Err(BuildError::InvalidRate(self.requests_per_second));
+        }
+        let mut http = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .user_agent(self.user_agent);
+        if let Some(proxy) = self.proxy {
+            http = http.proxy(Proxy::all(proxy)?);
+        }
+        Ok(OsrsClient {
+            http: http.build()?,
+            base_url: self.base_url,
+            limiter: Arc::new(RateLimiter::new(self.requests_per_second, self.burst)),
+            retry: self.retry,
+        })
+    }
+}
+
+/// Why `OsrsClientBuilder::build` failed.
+#[derive(Debug)]
+pub enum BuildError {
+    /// `requests_per_second` was zero, negative or not a number.
+    InvalidRate(f64),
+    /// The HTTP client rejected the proxy or TLS setup.
+    Http(reqwest::Error),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::InvalidRate(rate) => write!(
+                f,
+                "requests per second must be a positive number, got {}",
+                rate
+            ),
+            BuildError::Http(err) => write!(f, "failed to build HTTP client: {}", err),
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BuildError::Http(err) => Some(err),
+            BuildError::InvalidRate(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for BuildError {
+    fn from(err: reqwest::Error) -> Self {
+        BuildError::Http(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        fixtures::{self, lite_body},
+        osrs::{layout::HiscoreLayout, HiscoreBackend},
+    };
+
+    /// A client for `base_url` that retries quickly.
+    fn retrying(base_url: String) -> OsrsClient {
+        OsrsClient::builder()
+            .base_url(base_url)
+            .requests_per_second(1000.0)
+            .retry(RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(10),
+            })
+            .build()
+            .unwrap()
+    }
+
+    async fn fetch(osrs: &OsrsClient) -> Result<(), OsrsError> {
+        osrs.user_hiscore(
+            HiscoreBackend::Lite,
+            GameMode::Seasonal,
+            "Player".to_string(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    #[test]
+    fn rejects_rates_that_never_refill() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let built = OsrsClient::builder().requests_per_second(rate).build();
+            assert!(matches!(built, Err(BuildError::InvalidRate(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_when_rate_limited() {
+        let attempts = AtomicUsize::new(0);
+        let (base_url, requests) =
+            fixtures::serve(move |_| match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => (503, String::new()),
+                _ => (200, lite_body(HiscoreLayout::current(), &[])),
+            })
+            .await;
+
+        fetch(&retrying(base_url)).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (base_url, requests) = fixtures::serve(|_| (503, String::new())).await;
+
+        let result = fetch(&retrying(base_url)).await;
+        assert!(matches!(result, Err(OsrsError::RateLimited { .. })));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_a_missing_player() {
+        let (base_url, requests) = fixtures::serve(|_| (404, String::new())).await;
+
+        let result = fetch(&retrying(base_url)).await;
+        assert!(matches!(result, Err(OsrsError::NotFound)));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,90 @@
+use std::{error::Error, fmt, time::Duration};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+
+use super::LayoutError;
+
+#[derive(Debug)]
+pub enum OsrsError {
+    /// The hiscores have no player by that name.
+    NotFound,
+    /// Jagex asked us to slow down (HTTP 429 or 503).
+    RateLimited { retry_after: Option<Duration> },
+    /// The request never got a response.
+    Network(reqwest::Error),
+    /// Any other non-success status.
+    UnexpectedStatus(StatusCode),
+    /// The leaderboard page no longer has the markup we scrape.
+    HtmlLayout(String),
+    /// A player's hiscores no longer match the layout table.
+    CsvLayout(LayoutError),
+}
+
+impl OsrsError {
+    /// Whether sending the same request again later could succeed: rate
+    /// limits, server errors and timeouts.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            OsrsError::RateLimited { .. } => true,
+            OsrsError::UnexpectedStatus(status) => status.is_server_error(),
+            OsrsError::Network(err) => err.is_timeout() || err.is_connect(),
+            _ => false,
+        }
+    }
+
+    pub(super) fn from_response(res: &Response) -> Option<OsrsError> {
+        match res.status() {
+            status if status.is_success() => None,
+            StatusCode::NOT_FOUND => Some(OsrsError::NotFound),
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                Some(OsrsError::RateLimited {
+                    retry_after: res
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                        .map(Duration::from_secs),
+                })
+            }
+            status => Some(OsrsError::UnexpectedStatus(status)),
+        }
+    }
+}
+
+impl fmt::Display for OsrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OsrsError::NotFound => f.write_str("player not found"),
+            OsrsError::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(f, "rate limited, retry after {:?}", retry_after),
+            OsrsError::RateLimited { retry_after: None } => f.write_str("rate limited"),
+            OsrsError::Network(err) => write!(f, "network error: {}", err),
+            OsrsError::UnexpectedStatus(status) => write!(f, "unexpected status {}", status),
+            OsrsError::HtmlLayout(err) => write!(f, "hiscores page layout changed: {}", err),
+            OsrsError::CsvLayout(err) => write!(f, "hiscores layout changed: {}", err),
+        }
+    }
+}
+
+impl Error for OsrsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OsrsError::Network(err) => Some(err),
+            OsrsError::CsvLayout(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for OsrsError {
+    fn from(err: reqwest::Error) -> Self {
+        OsrsError::Network(err)
+    }
+}
+
+impl From<LayoutError> for OsrsError {
+    fn from(err: LayoutError) -> Self {
+        OsrsError::CsvLayout(err)
+    }
+}
@@ -0,0 +1,662 @@
+use std::{error::Error, fmt};
+
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+
+use super::{Hiscore, HiscoreActivities, HiscoreActivityEntry, HiscoreSkillEntry, HiscoreSkills};
+
+/// How a line of the lite hiscores is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Skill,
+    Activity,
+    /// Present in the response but not stored.
+    Ignored,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutEntry {
+    /// Key the entry is stored under in `HiscoreSkills`/`HiscoreActivities`.
+    pub key: &'static str,
+    /// Name Jagex uses for the entry.
+    pub name: &'static str,
+    pub kind: MetricKind,
+}
+
+const fn skill(key: &'static str, name: &'static str) -> LayoutEntry {
+    LayoutEntry {
+        key,
+        name,
+        kind: MetricKind::Skill,
+    }
+}
+
+const fn activity(key: &'static str, name: &'static str) -> LayoutEntry {
+    LayoutEntry {
+        key,
+        name,
+        kind: MetricKind::Activity,
+    }
+}
+
+const fn ignored(name: &'static str) -> LayoutEntry {
+    LayoutEntry {
+        key: "",
+        name,
+        kind: MetricKind::Ignored,
+    }
+}
+
+const fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    match NaiveDate::from_ymd_opt(year, month, day) {
+        Some(date) => date,
+        None => panic!("invalid layout date"),
+    }
+}
+
+/// Line order of `index_lite.ws` from a given date onwards.
+#[derive(Debug)]
+pub struct HiscoreLayout {
+    pub since: NaiveDate,
+    pub entries: &'static [LayoutEntry],
+}
+
+/// Every known layout, oldest first. When Jagex adds a boss, add a new layout
+/// with the date it went live rather than editing an existing one.
+pub const LAYOUTS: &[HiscoreLayout] = &[HiscoreLayout {
+    since: date(2023, 7, 26),
+    entries: LAYOUT_2023_07_26,
+}];
+
+const LAYOUT_2023_07_26: &[LayoutEntry] = &[
+    skill("overall", "Overall"),
+    skill("attack", "Attack"),
+    skill("defence", "Defence"),
+    skill("strength", "Strength"),
+    skill("hitpoints", "Hitpoints"),
+    skill("ranged", "Ranged"),
+    skill("prayer", "Prayer"),
+    skill("magic", "Magic"),
+    skill("cooking", "Cooking"),
+    skill("woodcutting", "Woodcutting"),
+    skill("fletching", "Fletching"),
+    skill("fishing", "Fishing"),
+    skill("firemaking", "Firemaking"),
+    skill("crafting", "Crafting"),
+    skill("smithing", "Smithing"),
+    skill("mining", "Mining"),
+    skill("herblore", "Herblore"),
+    skill("agility", "Agility"),
+    skill("thieving", "Thieving"),
+    skill("slayer", "Slayer"),
+    skill("farming", "Farming"),
+    skill("runecraft", "Runecraft"),
+    skill("hunter", "Hunter"),
+    skill("construction", "Construction"),
+    activity("leaguePoints", "League Points"),
+    ignored("Deadman Points"),
+    ignored("Bounty Hunter - Hunter"),
+    ignored("Bounty Hunter - Rogue"),
+    ignored("Bounty Hunter (Legacy) - Hunter"),
+    ignored("Bounty Hunter (Legacy) - Rogue"),
+    activity("clueScrollsAll", "Clue Scrolls (all)"),
+    activity("clueScrollsBeginner", "Clue Scrolls (beginner)"),
+    activity("clueScrollsEasy", "Clue Scrolls (easy)"),
+    activity("clueScrollsMedium", "Clue Scrolls (medium)"),
+    activity("clueScrollsHard", "Clue Scrolls (hard)"),
+    activity("clueScrollsElite", "Clue Scrolls (elite)"),
+    activity("clueScrollsMaster", "Clue Scrolls (master)"),
+    ignored("LMS - Rank"),
+    ignored("PvP Arena - Rank"),
+    activity("soulWarsZeal", "Soul Wars Zeal"),
+    activity("riftsClosed", "Rifts closed"),
+    activity("abyssalSire", "Abyssal Sire"),
+    activity("alchemicalHydra", "Alchemical Hydra"),
+    activity("artio", "Artio"),
+    activity("barrowsChests", "Barrows Chests"),
+    activity("bryophyta", "Bryophyta"),
+    activity("callisto", "Callisto"),
+    activity("calvarion", "Calvar'ion"),
+    activity("cerberus", "Cerberus"),
+    activity("chambersOfXeric", "Chambers of Xeric"),
+    activity(
+        "chambersOfXericChallengeMode",
+        "Chambers of Xeric: Challenge Mode",
+    ),
+    activity("chaosElemental", "Chaos Elemental"),
+    activity("chaosFanatic", "Chaos Fanatic"),
+    activity("commanderZilyana", "Commander Zilyana"),
+    activity("corporealBeast", "Corporeal Beast"),
+    activity("crazyArchaeologist", "Crazy Archaeologist"),
+    activity("dagannothPrime", "Dagannoth Prime"),
+    activity("dagannothRex", "Dagannoth Rex"),
+    activity("dagannothSupreme", "Dagannoth Supreme"),
+    activity("derangedArchaeologist", "Deranged Archaeologist"),
+    activity("dukeSucellus", "Duke Sucellus"),
+    activity("generalGraardor", "General Graardor"),
+    activity("giantMole", "Giant Mole"),
+    activity("grotesqueGuardians", "Grotesque Guardians"),
+    activity("hespori", "Hespori"),
+    activity("kalphiteQueen", "Kalphite Queen"),
+    activity("kingBlackDragon", "King Black Dragon"),
+    activity("kraken", "Kraken"),
+    activity("kreearra", "Kree'Arra"),
+    activity("krilTsutsaroth", "K'ril Tsutsaroth"),
+    activity("mimic", "Mimic"),
+    activity("nex", "Nex"),
+    activity("nightmare", "Nightmare"),
+    activity("phosanisNightmare", "Phosani's Nightmare"),
+    activity("obor", "Obor"),
+    activity("phantomMuspah", "Phantom Muspah"),
+    activity("sarachnis", "Sarachnis"),
+    activity("scorpia", "Scorpia"),
+    activity("skotizo", "Skotizo"),
+    activity("spindel", "Spindel"),
+    activity("tempoross", "Tempoross"),
+    activity("theGauntlet", "The Gauntlet"),
+    activity("theCorruptedGauntlet", "The Corrupted Gauntlet"),
+    activity("theLeviathan", "The Leviathan"),
+    activity("theWhisperer", "The Whisperer"),
+    activity("theatreOfBlood", "Theatre of Blood"),
+    activity("theatreOfBloodHardMode", "Theatre of Blood: Hard Mode"),
+    activity("thermonuclearSmokeDevil", "Thermonuclear Smoke Devil"),
+    activity("tombsOfAmascut", "Tombs of Amascut"),
+    activity("tombsOfAmascutExpertMode", "Tombs of Amascut: Expert Mode"),
+    activity("tzkalZuk", "TzKal-Zuk"),
+    activity("tztokJad", "TzTok-Jad"),
+    activity("vardorvis", "Vardorvis"),
+    activity("venenatis", "Venenatis"),
+    activity("vetion", "Vet'ion"),
+    activity("vorkath", "Vorkath"),
+    activity("wintertodt", "Wintertodt"),
+    activity("zalcano", "Zalcano"),
+    activity("zulrah", "Zulrah"),
+];
+
+impl HiscoreLayout {
+    pub fn current() -> &'static HiscoreLayout {
+        Self::at(Utc::now().date_naive())
+    }
+
+    /// The layout in effect on `date`, falling back to the oldest known one.
+    pub fn at(date: NaiveDate) -> &'static HiscoreLayout {
+        LAYOUTS
+            .iter()
+            .rev()
+            .find(|layout| layout.since <= date)
+            .unwrap_or(&LAYOUTS[0])
+    }
+
+    pub fn parse(&self, body: &str) -> Result<Hiscore, LayoutError> {
+        let lines: Vec<&str> = body.trim_end().lines().collect();
+        if lines.len() != self.entries.len() {
+            return Err(LayoutError::UnexpectedLineCount {
+                layout: self.since,
+                expected: self.entries.len(),
+                found: lines.len(),
+            });
+        }
+
+        let mut skills = Vec::new();
+        let mut activities = HiscoreActivities::default();
+        for (entry, line) in self.entries.iter().zip(lines) {
+            match entry.kind {
+                MetricKind::Skill => {
+                    let skill = extract_skill_entry(line).ok_or(LayoutError::BadSkillEntry {
+                        skill: entry.name,
+                        line: line.to_string(),
+                    })?;
+                    skills.push((entry.key, skill));
+                }
+                MetricKind::Activity => {
+                    activities
+                        .0
+                        .insert(entry.key.to_string(), extract_activity_entry(line));
+                }
+                MetricKind::Ignored => {}
+            }
+        }
+
+        finish(skills, activities)
+    }
+
+    /// Parses `index_lite.json`, matching entries by name instead of by
+    /// position. Names the layout does not know about are skipped.
+    pub fn parse_json(&self, body: &str) -> Result<Hiscore, LayoutError> {
+        let json: JsonHiscore =
+            serde_json::from_str(body).map_err(|err| LayoutError::Json(err.to_string()))?;
+
+        let mut skills = Vec::new();
+        for skill in json.skills {
+            if let Some(entry) = self.find(&skill.name, MetricKind::Skill) {
+                skills.push((
+                    entry.key,
+                    RawSkill {
+                        rank: skill.rank,
+                        level: skill.level,
+                        xp: skill.xp,
+                    },
+                ));
+            }
+        }
+
+        let mut activities = HiscoreActivities::default();
+        for entry in self.entries {
+            if entry.kind == MetricKind::Activity {
+                activities.0.insert(entry.key.to_string(), None);
+            }
+        }
+        for activity in json.activities {
+            if let Some(entry) = self.find(&activity.name, MetricKind::Activity) {
+                activities
+                    .0
+                    .insert(entry.key.to_string(), activity.to_entry());
+            }
+        }
+
+        finish(skills, activities)
+    }
+
+    fn find(&self, name: &str, kind: MetricKind) -> Option<&'static LayoutEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.kind == kind && entry.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A skill as the hiscores report it. Unranked skills come back with a rank
+/// of -1 and usually -1 or placeholder values for level and xp.
+struct RawSkill {
+    rank: i64,
+    level: i64,
+    xp: i64,
+}
+
+impl RawSkill {
+    fn is_ranked(&self) -> bool {
+        self.rank >= 0 && self.level >= 0 && self.xp >= 0
+    }
+}
+
+/// The lowest level and xp a skill can have on a fresh account.
+fn minimum(key: &str) -> (u64, u64) {
+    match key {
+        "hitpoints" => (10, 1154),
+        _ => (1, 0),
+    }
+}
+
+fn finish(
+    raw: Vec<(&'static str, RawSkill)>,
+    activities: HiscoreActivities,
+) -> Result<Hiscore, LayoutError> {
+    let overall = raw
+        .iter()
+        .find(|(key, skill)| *key == "overall" && skill.is_ranked())
+        .map(|(_, skill)| skill);
+    let others = || raw.iter().filter(|(key, _)| *key != "overall");
+    let ranked_level: u64 = others()
+        .filter(|(_, skill)| skill.is_ranked())
+        .map(|(_, skill)| skill.level as u64)
+        .sum();
+    let ranked_xp: u64 = others()
+        .filter(|(_, skill)| skill.is_ranked())
+        .map(|(_, skill)| skill.xp as u64)
+        .sum();
+    let unranked: Vec<&str> = others()
+        .filter(|(_, skill)| !skill.is_ranked())
+        .map(|(key, _)| *key)
+        .collect();
+
+    let mut skills = serde_json::Map::new();
+    for (key, skill) in &raw {
+        let entry = if skill.is_ranked() {
+            HiscoreSkillEntry::Ranked {
+                xp: skill.xp as u64,
+                level: skill.level as u64,
+                rank: skill.rank as u64,
+            }
+        } else {
+            let (min_level, min_xp) = minimum(key);
+            let mut level = min_level.max(skill.level.max(0) as u64);
+            let mut xp = min_xp.max(skill.xp.max(0) as u64);
+            if *key == "overall" {
+                // Overall is at least the ranked skills plus the minimum of
+                // every unranked one.
+                let (unranked_level, unranked_xp) = unranked
+                    .iter()
+                    .map(|key| minimum(key))
+                    .fold((0, 0), |(l, x), (ml, mx)| (l + ml, x + mx));
+                level = level.max(ranked_level + unranked_level);
+                xp = xp.max(ranked_xp + unranked_xp);
+            } else if let (Some(overall), [_]) = (overall, unranked.as_slice()) {
+                // The only unranked skill accounts for whatever overall does
+                // not explain.
+                level = level.max((overall.level as u64).saturating_sub(ranked_level));
+                xp = xp.max((overall.xp as u64).saturating_sub(ranked_xp));
+            }
+            HiscoreSkillEntry::Unranked { xp, level }
+        };
+        skills.insert(
+            key.to_string(),
+            serde_json::to_value(entry).map_err(|err| LayoutError::Skills(err.to_string()))?,
+        );
+    }
+
+    let skills: HiscoreSkills = serde_json::from_value(serde_json::Value::Object(skills))
+        .map_err(|err| LayoutError::Skills(err.to_string()))?;
+    Ok(Hiscore { skills, activities })
+}
+
+#[derive(Deserialize)]
+struct JsonHiscore {
+    skills: Vec<JsonSkill>,
+    activities: Vec<JsonActivity>,
+}
+
+#[derive(Deserialize)]
+struct JsonSkill {
+    name: String,
+    rank: i64,
+    level: i64,
+    xp: i64,
+}
+
+#[derive(Deserialize)]
+struct JsonActivity {
+    name: String,
+    rank: i64,
+    score: i64,
+}
+
+impl JsonActivity {
+    fn to_entry(&self) -> Option<HiscoreActivityEntry> {
+        Some(HiscoreActivityEntry {
+            rank: self.rank.try_into().ok()?,
+            score: self.score.try_into().ok()?,
+        })
+    }
+}
+
+fn extract_skill_entry(entry: &str) -> Option<RawSkill> {
+    let entries: Vec<i64> = entry
+        .split(',')
+        .map(|s| s.parse::<i64>().ok())
+        .collect::<Option<Vec<i64>>>()?;
+    Some(RawSkill {
+        rank: *entries.first()?,
+        level: *entries.get(1)?,
+        xp: *entries.get(2)?,
+    })
+}
+
+fn extract_activity_entry(entry: &str) -> Option<HiscoreActivityEntry> {
+    let entries: Vec<u64> = entry
+        .split(',')
+        .map(|s| s.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    Some(HiscoreActivityEntry {
+        rank: *entries.first()?,
+        score: *entries.get(1)?,
+    })
+}
+
+#[derive(Debug)]
+pub enum LayoutError {
+    /// The response has a different number of lines than the layout, most
+    /// likely because Jagex added or removed an entry.
+    UnexpectedLineCount {
+        layout: NaiveDate,
+        expected: usize,
+        found: usize,
+    },
+    BadSkillEntry {
+        skill: &'static str,
+        line: String,
+    },
+    /// The layout does not cover every field of `HiscoreSkills`.
+    Skills(String),
+    /// `index_lite.json` did not have the expected shape.
+    Json(String),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::UnexpectedLineCount {
+                layout,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} hiscore lines for layout {}, found {}",
+                expected, layout, found
+            ),
+            LayoutError::BadSkillEntry { skill, line } => {
+                write!(f, "bad hiscore entry for {}: {:?}", skill, line)
+            }
+            LayoutError::Skills(err) => write!(f, "incomplete skills: {}", err),
+            LayoutError::Json(err) => write!(f, "bad hiscores json: {}", err),
+        }
+    }
+}
+
+impl Error for LayoutError {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{fixtures::lite_body, osrs::compare_hiscores};
+
+    /// The `index_lite.json` equivalent of `lite_body`.
+    fn json_body(lite: &str, layout: &HiscoreLayout) -> String {
+        let mut skills = Vec::new();
+        let mut activities = Vec::new();
+        for (entry, line) in layout.entries.iter().zip(lite.lines()) {
+            let numbers: Vec<i64> = line.split(',').map(|n| n.parse().unwrap()).collect();
+            match entry.kind {
+                MetricKind::Skill => skills.push(json!({
+                    "id": skills.len(),
+                    "name": entry.name,
+                    "rank": numbers[0],
+                    "level": numbers[1],
+                    "xp": numbers[2],
+                })),
+                MetricKind::Activity | MetricKind::Ignored => activities.push(json!({
+                    "id": activities.len(),
+                    "name": entry.name,
+                    "rank": numbers[0],
+                    "score": numbers[1],
+                })),
+            }
+        }
+        json!({ "skills": skills, "activities": activities }).to_string()
+    }
+
+    fn skill<'a>(hiscore: &'a Hiscore, key: &str) -> &'a HiscoreSkillEntry {
+        hiscore.skills().get(key).unwrap()
+    }
+
+    #[test]
+    fn layouts_are_oldest_first_with_unique_keys() {
+        for pair in LAYOUTS.windows(2) {
+            assert!(pair[0].since < pair[1].since);
+        }
+        for layout in LAYOUTS {
+            let mut keys: Vec<&str> = layout
+                .entries
+                .iter()
+                .filter(|entry| entry.kind != MetricKind::Ignored)
+                .map(|entry| entry.key)
+                .collect();
+            let count = keys.len();
+            keys.sort_unstable();
+            keys.dedup();
+            assert_eq!(keys.len(), count, "duplicate key in {}", layout.since);
+        }
+    }
+
+    #[test]
+    fn layout_at_picks_the_latest_one_in_effect() {
+        let first = &LAYOUTS[0];
+        assert_eq!(HiscoreLayout::at(first.since).since, first.since);
+        assert_eq!(HiscoreLayout::at(date(2000, 1, 1)).since, first.since);
+        assert_eq!(
+            HiscoreLayout::current().since,
+            LAYOUTS.last().unwrap().since
+        );
+    }
+
+    #[test]
+    fn every_layout_fills_every_skill() {
+        for layout in LAYOUTS {
+            let hiscore = layout.parse(&lite_body(layout, &[])).unwrap();
+            for entry in layout.entries {
+                if entry.kind == MetricKind::Skill {
+                    assert!(hiscore.skills().get(entry.key).is_some(), "{}", entry.key);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parses_ranked_skills_and_activities() {
+        let layout = &LAYOUTS[0];
+        let hiscore = layout.parse(&lite_body(layout, &[])).unwrap();
+
+        assert_eq!(
+            skill(&hiscore, "attack"),
+            &HiscoreSkillEntry::Ranked {
+                xp: 101_333,
+                level: 50,
+                rank: 5000
+            }
+        );
+        assert_eq!(skill(&hiscore, "overall").level(), 23 * 50);
+        let clues = hiscore.activities().get("clueScrollsAll").unwrap();
+        assert_eq!((clues.rank(), clues.score()), (800, 12));
+        assert!(hiscore.activities().get("zulrah").is_none());
+        assert!(hiscore.activities().get("deadmanPoints").is_none());
+    }
+
+    #[test]
+    fn rejects_a_different_line_count() {
+        let layout = &LAYOUTS[0];
+        let body = lite_body(layout, &[]) + "-1,-1\n";
+        match layout.parse(&body) {
+            Err(LayoutError::UnexpectedLineCount {
+                expected, found, ..
+            }) => assert_eq!((expected, found), (layout.entries.len(), expected + 1)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_a_malformed_skill_line() {
+        let layout = &LAYOUTS[0];
+        let body = lite_body(layout, &[("magic", "1,2")]);
+        assert!(matches!(
+            layout.parse(&body),
+            Err(LayoutError::BadSkillEntry { skill: "Magic", .. })
+        ));
+    }
+
+    #[test]
+    fn unranked_skills_get_the_fresh_account_minimum() {
+        let layout = &LAYOUTS[0];
+        let body = lite_body(
+            layout,
+            &[
+                ("hitpoints", "-1,-1,-1"),
+                ("magic", "-1,-1,-1"),
+                ("overall", "-1,-1,-1"),
+            ],
+        );
+        let hiscore = layout.parse(&body).unwrap();
+
+        assert_eq!(
+            skill(&hiscore, "hitpoints"),
+            &HiscoreSkillEntry::Unranked {
+                xp: 1154,
+                level: 10
+            }
+        );
+        assert_eq!(
+            skill(&hiscore, "magic"),
+            &HiscoreSkillEntry::Unranked { xp: 0, level: 1 }
+        );
+        // 21 ranked skills at level 50 plus the two minimums.
+        assert_eq!(
+            skill(&hiscore, "overall"),
+            &HiscoreSkillEntry::Unranked {
+                xp: 21 * 101_333 + 1154,
+                level: 21 * 50 + 10 + 1
+            }
+        );
+    }
+
+    #[test]
+    fn a_single_unranked_skill_is_what_overall_does_not_explain() {
+        let layout = &LAYOUTS[0];
+        let overall = format!("1200,{},{}", 22 * 50 + 7, 22 * 101_333 + 800);
+        let body = lite_body(
+            layout,
+            &[("overall", overall.as_str()), ("cooking", "-1,-1,-1")],
+        );
+        let hiscore = layout.parse(&body).unwrap();
+
+        assert_eq!(
+            skill(&hiscore, "cooking"),
+            &HiscoreSkillEntry::Unranked { xp: 800, level: 7 }
+        );
+    }
+
+    #[test]
+    fn json_matches_lite() {
+        let layout = &LAYOUTS[0];
+        let lite = lite_body(layout, &[("hitpoints", "-1,-1,-1")]);
+        let from_lite = layout.parse(&lite).unwrap();
+        let from_json = layout.parse_json(&json_body(&lite, layout)).unwrap();
+
+        assert_eq!(from_lite, from_json);
+        assert!(compare_hiscores(&from_lite, &from_json).is_empty());
+    }
+
+    #[test]
+    fn json_skips_unknown_names() {
+        let layout = &LAYOUTS[0];
+        let lite = lite_body(layout, &[]);
+        let mut json: serde_json::Value = serde_json::from_str(&json_body(&lite, layout)).unwrap();
+        json["activities"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "id": 999, "name": "Brand New Boss", "rank": 1, "score": 5 }));
+
+        let hiscore = layout.parse_json(&json.to_string()).unwrap();
+        assert_eq!(hiscore, layout.parse(&lite).unwrap());
+    }
+
+    #[test]
+    fn cross_check_reports_disagreements() {
+        let layout = &LAYOUTS[0];
+        let lite = lite_body(layout, &[]);
+        let from_lite = layout.parse(&lite).unwrap();
+        let changed = lite_body(layout, &[("zulrah", "3000,40")]);
+        let from_json = layout.parse_json(&json_body(&changed, layout)).unwrap();
+
+        let disagreements = compare_hiscores(&from_lite, &from_json);
+        assert_eq!(disagreements.len(), 1);
+        assert_eq!(disagreements[0].key, "activities.zulrah");
+        assert_eq!(disagreements[0].lite, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn bad_json_is_an_error() {
+        assert!(matches!(
+            LAYOUTS[0].parse_json("{\"skills\": 3}"),
+            Err(LayoutError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::{sync::Mutex, time::Instant};
+
+/// Token bucket shared by every request an `OsrsClient` makes.
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: f64, burst: u32) -> RateLimiter {
+        let burst = f64::from(burst.max(1));
+        RateLimiter {
+            per_second,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent. Callers reserve their token up
+    /// front, so waiters are released in the order they arrived.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().await;
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+            bucket.refilled_at = now;
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / self.per_second)
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// How often and how patiently a failed request is retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter for the given retry (starting at 0),
+    /// never shorter than what the server asked for.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let jittered = backoff / 2 + backoff.mul_f64(rand::thread_rng().gen::<f64>() / 2.0);
+        jittered.max(retry_after.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_once_the_burst_is_spent() {
+        let limiter = RateLimiter::new(20.0, 2);
+        let started = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(started.elapsed() < Duration::from_millis(40));
+        limiter.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let retry = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        for _ in 0..20 {
+            let first = retry.delay(0, None);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = retry.delay(2, None);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            let capped = retry.delay(10, None);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn delay_is_never_shorter_than_retry_after() {
+        let retry = RetryPolicy::default();
+        let retry_after = Duration::from_secs(5);
+        assert_eq!(retry.delay(0, Some(retry_after)), retry_after);
+    }
+}
@@ -0,0 +1,6 @@
+//! The polling loops behind each binary.
+
+pub mod discover;
+pub mod schedule;
+pub mod stats;
+pub mod top_players;
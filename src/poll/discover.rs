@@ -0,0 +1,212 @@
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use mongodb::bson::DateTime;
+
+use crate::config::Config;
+use crate::db::{CrawlEntry, Store, StoreError};
+use crate::osrs::{GameMode, Leaderboard, OsrsClient, OsrsError};
+
+/// Picks up the latest unfinished crawl of `leaderboard`, or starts a new one
+/// from the first page.
+async fn resume_or_start(
+    store: &dyn Store,
+    game_mode: GameMode,
+    leaderboard: Leaderboard,
+) -> Result<CrawlEntry, StoreError> {
+    if let Some(crawl) = store.unfinished_crawl(game_mode, leaderboard).await? {
+        println!(
+            "Resuming {} {} crawl at page {}",
+            game_mode, leaderboard, crawl.page
+        );
+        return Ok(crawl);
+    }
+    println!("Starting {} {} crawl", game_mode, leaderboard);
+    let mut crawl = CrawlEntry {
+        id: None,
+        game_mode,
+        category: leaderboard.category,
+        table: leaderboard.table,
+        page: 1,
+        started_at: DateTime::now(),
+        finished_at: None,
+        pages_done: 0,
+        new_names: 0,
+    };
+    store.save_crawl(&mut crawl).await?;
+    Ok(crawl)
+}
+
+/// Fetches the crawl's current page, records its usernames and moves the
+/// checkpoint on. A finished crawl is replaced by a new one.
+async fn crawl_page(
+    store: &dyn Store,
+    osrs: &OsrsClient,
+    game_mode: GameMode,
+    leaderboard: Leaderboard,
+    crawl: &mut CrawlEntry,
+) -> Result<(), StoreError> {
+    println!(
+        "Updating usernames with {} {} page {}...",
+        game_mode, leaderboard, crawl.page
+    );
+
+    match osrs
+        .hiscores_index(game_mode, leaderboard, crawl.page as usize)
+        .await
+    {
+        Ok(page) => {
+            let names: Vec<&str> = page.rows.iter().map(|row| row.name()).collect();
+            let counts = match store.upsert_usernames(game_mode, &names).await {
+                Ok(counts) => counts,
+                Err(err) => {
+                    // Leave the checkpoint alone so the page is retried.
+                    println!("Failed to save page {}: {}", crawl.page, err);
+                    return Ok(());
+                }
+            };
+            println!(
+                "Page {}: {} new names, {} already known",
+                crawl.page, counts.inserted, counts.matched
+            );
+
+            crawl.pages_done += 1;
+            crawl.new_names += counts.inserted;
+            if page.is_last {
+                crawl.finished_at = Some(DateTime::now());
+            } else {
+                crawl.page += 1;
+            }
+            if let Err(err) = store.save_crawl(crawl).await {
+                println!("Failed to save crawl checkpoint: {:?}", err);
+            }
+
+            if let Some(finished_at) = crawl.finished_at {
+                let elapsed = finished_at.timestamp_millis() - crawl.started_at.timestamp_millis();
+                println!(
+                    "Finished {} {} crawl: {} pages, {} new names in {}s",
+                    game_mode,
+                    leaderboard,
+                    crawl.pages_done,
+                    crawl.new_names,
+                    elapsed / 1000
+                );
+                *crawl = resume_or_start(store, game_mode, leaderboard).await?;
+            }
+        }
+        Err(OsrsError::RateLimited { retry_after }) => {
+            let wait = retry_after.unwrap_or(Duration::from_secs(60));
+            println!("Rate limited, backing off for {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+        Err(err @ (OsrsError::HtmlLayout(_) | OsrsError::CsvLayout(_))) => {
+            eprintln!("ALERT: {} on hiscores page {}", err, crawl.page);
+        }
+        Err(err) => println!("Failed to load hiscores page {}: {}", crawl.page, err),
+    }
+    Ok(())
+}
+
+/// Crawls `CRAWL_LEADERBOARD`, or the game mode's main leaderboard, page by
+/// page for usernames to poll, starting over once it reaches the last page.
+pub async fn run(
+    config: &Config,
+    store: &Arc<dyn Store>,
+    osrs: &OsrsClient,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let game_mode = config.game_mode;
+    let leaderboard = match env::var("CRAWL_LEADERBOARD") {
+        Ok(leaderboard) => leaderboard.parse()?,
+        Err(_) => Leaderboard::main(game_mode),
+    };
+
+    let mut crawl = resume_or_start(store.as_ref(), game_mode, leaderboard).await?;
+
+    loop {
+        crawl_page(store.as_ref(), osrs, game_mode, leaderboard, &mut crawl).await?;
+
+        println!("Waiting....");
+        tokio::time::sleep(Duration::from_secs(30)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::memory::MemoryStore, fixtures};
+
+    fn crawl_at(page: u64) -> CrawlEntry {
+        CrawlEntry {
+            id: None,
+            game_mode: GameMode::Seasonal,
+            category: Leaderboard::LEAGUE_POINTS.category,
+            table: Leaderboard::LEAGUE_POINTS.table,
+            page,
+            started_at: DateTime::from_millis(0),
+            finished_at: None,
+            pages_done: 2,
+            new_names: 50,
+        }
+    }
+
+    async fn store_with(crawl: CrawlEntry) -> MemoryStore {
+        let store = MemoryStore::default();
+        store.save_crawl(&mut crawl.clone()).await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn resumes_an_unfinished_crawl() {
+        let store = store_with(crawl_at(3)).await;
+        let (osrs, requests) = fixtures::hiscores(|_| {
+            (
+                200,
+                fixtures::leaderboard_page(
+                    &[(51, "Found", 100)],
+                    Some("overall?category_type=1&table=0&page=4"),
+                ),
+            )
+        })
+        .await;
+
+        let (game_mode, leaderboard) = (GameMode::Seasonal, Leaderboard::LEAGUE_POINTS);
+        let mut crawl = resume_or_start(&store, game_mode, leaderboard)
+            .await
+            .unwrap();
+        crawl_page(&store, &osrs, game_mode, leaderboard, &mut crawl)
+            .await
+            .unwrap();
+
+        assert_eq!(requests.lock().unwrap()[0].query["page"], "3");
+        assert_eq!(crawl.page, 4);
+        let data = store.data();
+        assert_eq!(data.usernames[0].display_name, "Found");
+        assert_eq!(data.crawls.len(), 1);
+        assert_eq!(data.crawls[0].page, 4);
+        assert_eq!(data.crawls[0].pages_done, 3);
+        assert_eq!(data.crawls[0].new_names, 51);
+    }
+
+    #[tokio::test]
+    async fn starts_over_after_the_last_page() {
+        let store = store_with(crawl_at(7)).await;
+        let (osrs, _) =
+            fixtures::hiscores(|_| (200, fixtures::leaderboard_page(&[(301, "Last", 1)], None)))
+                .await;
+
+        let (game_mode, leaderboard) = (GameMode::Seasonal, Leaderboard::LEAGUE_POINTS);
+        let mut crawl = resume_or_start(&store, game_mode, leaderboard)
+            .await
+            .unwrap();
+        crawl_page(&store, &osrs, game_mode, leaderboard, &mut crawl)
+            .await
+            .unwrap();
+
+        assert_eq!((crawl.page, crawl.pages_done), (1, 0));
+        let data = store.data();
+        assert_eq!(data.crawls.len(), 2);
+        assert!(data.crawls[0].finished_at.is_some());
+        assert_eq!(data.crawls[1].id, crawl.id);
+    }
+}
@@ -0,0 +1,135 @@
+use std::{env, time::Duration};
+
+use mongodb::bson::DateTime;
+
+/// How often a player is polled. Players who just gained xp are polled every
+/// `min_interval`; the interval doubles for every `doubling_period` they stay
+/// unchanged, up to `max_interval`.
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+    pub doubling_period: Duration,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            min_interval: Duration::from_secs(60 * 15),
+            max_interval: Duration::from_secs(60 * 60 * 24 * 7),
+            doubling_period: Duration::from_secs(60 * 60 * 24),
+        }
+    }
+}
+
+impl Schedule {
+    /// Reads `POLL_MIN_INTERVAL_MINS`, `POLL_MAX_INTERVAL_HOURS` and
+    /// `POLL_DOUBLING_HOURS`, using the defaults for any that are unset.
+    pub fn from_env() -> Result<Schedule, Box<dyn std::error::Error + Send + Sync>> {
+        let mut schedule = Schedule::default();
+        if let Ok(mins) = env::var("POLL_MIN_INTERVAL_MINS") {
+            schedule.min_interval = Duration::from_secs(mins.parse::<u64>()? * 60);
+        }
+        if let Ok(hours) = env::var("POLL_MAX_INTERVAL_HOURS") {
+            schedule.max_interval = Duration::from_secs(hours.parse::<u64>()? * 60 * 60);
+        }
+        if let Ok(hours) = env::var("POLL_DOUBLING_HOURS") {
+            schedule.doubling_period = Duration::from_secs(hours.parse::<u64>()? * 60 * 60);
+        }
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    /// Checks the minimum interval is positive and no longer than the
+    /// maximum.
+    fn validate(&self) -> Result<(), String> {
+        if self.min_interval.is_zero() {
+            return Err("POLL_MIN_INTERVAL_MINS must be at least 1".to_string());
+        }
+        if self.min_interval > self.max_interval {
+            return Err(
+                "POLL_MIN_INTERVAL_MINS must not be longer than POLL_MAX_INTERVAL_HOURS"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Time between polls for a player whose stats last changed at
+    /// `last_changed_at`.
+    pub fn interval(&self, now: DateTime, last_changed_at: DateTime) -> Duration {
+        let idle = now
+            .timestamp_millis()
+            .saturating_sub(last_changed_at.timestamp_millis())
+            .max(0) as f64;
+        let doublings = idle / self.doubling_period.as_millis().max(1) as f64;
+        let interval = self.min_interval.as_secs_f64() * doublings.exp2();
+        Duration::from_secs_f64(interval.min(self.max_interval.as_secs_f64()))
+    }
+
+    pub fn next_poll_at(&self, now: DateTime, last_changed_at: DateTime) -> DateTime {
+        let interval = self.interval(now, last_changed_at);
+        DateTime::from_millis(now.timestamp_millis() + interval.as_millis() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    fn at(millis: i64) -> DateTime {
+        DateTime::from_millis(1_700_000_000_000 + millis)
+    }
+
+    #[test]
+    fn just_changed_players_are_polled_at_the_minimum() {
+        let schedule = Schedule::default();
+        assert_eq!(schedule.interval(at(0), at(0)), schedule.min_interval);
+    }
+
+    #[test]
+    fn interval_doubles_every_doubling_period() {
+        let schedule = Schedule::default();
+        assert_eq!(
+            schedule.interval(at(24 * HOUR), at(0)),
+            schedule.min_interval * 2
+        );
+        assert_eq!(
+            schedule.interval(at(72 * HOUR), at(0)),
+            schedule.min_interval * 8
+        );
+    }
+
+    #[test]
+    fn interval_stops_at_the_maximum() {
+        let schedule = Schedule::default();
+        assert_eq!(
+            schedule.interval(at(365 * 24 * HOUR), at(0)),
+            schedule.max_interval
+        );
+    }
+
+    #[test]
+    fn rejects_intervals_that_poll_every_cycle_or_overlap() {
+        assert!(Schedule::default().validate().is_ok());
+        let zero = Schedule {
+            min_interval: Duration::ZERO,
+            ..Schedule::default()
+        };
+        assert!(zero.validate().is_err());
+        let inverted = Schedule {
+            min_interval: Duration::from_secs(60 * 60 * 24 * 30),
+            ..Schedule::default()
+        };
+        assert!(inverted.validate().is_err());
+    }
+
+    #[test]
+    fn next_poll_is_one_interval_away() {
+        let schedule = Schedule::default();
+        let next = schedule.next_poll_at(at(24 * HOUR), at(0));
+        assert_eq!(next, at(24 * HOUR + 30 * 60 * 1000));
+    }
+}
@@ -0,0 +1,395 @@
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+
+use mongodb::bson::DateTime;
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::JoinSet,
+    time::Instant,
+};
+
+use super::schedule::Schedule;
+use crate::{
+    db::{self, ScheduledPoll, StatEntry, Store, StoreError, UsernameEntry},
+    osrs::{GameMode, HiscoreBackend, OsrsClient, OsrsError},
+};
+
+#[derive(Clone)]
+struct Poller {
+    osrs: OsrsClient,
+    backend: HiscoreBackend,
+    store: Arc<dyn Store>,
+    schedule: Schedule,
+    /// `db::stats_hash` of each player's latest snapshot, so unchanged stats
+    /// are spotted without touching the store.
+    latest_hashes: Arc<Mutex<HashMap<(String, GameMode), i64>>>,
+}
+
+enum Outcome {
+    Updated,
+    Unchanged,
+    Missing,
+    Failed,
+}
+
+/// What a polling cycle got through.
+#[derive(Default)]
+struct CycleStats {
+    updated: usize,
+    unchanged: usize,
+    missing: usize,
+    failed: usize,
+}
+
+impl CycleStats {
+    fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Updated => self.updated += 1,
+            Outcome::Unchanged => self.unchanged += 1,
+            Outcome::Missing => self.missing += 1,
+            Outcome::Failed => self.failed += 1,
+        }
+    }
+
+    fn merge(&mut self, other: CycleStats) {
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+        self.missing += other.missing;
+        self.failed += other.failed;
+    }
+
+    fn total(&self) -> usize {
+        self.updated + self.unchanged + self.missing + self.failed
+    }
+}
+
+/// Schedule updates a worker collects before writing them in one batch.
+const SCHEDULE_BATCH: usize = 100;
+
+impl Poller {
+    async fn new(
+        store: &Arc<dyn Store>,
+        osrs: &OsrsClient,
+        backend: HiscoreBackend,
+        schedule: Schedule,
+    ) -> Result<Poller, StoreError> {
+        Ok(Poller {
+            osrs: osrs.clone(),
+            backend,
+            store: store.clone(),
+            schedule,
+            latest_hashes: Arc::new(Mutex::new(store.latest_stats_hashes().await?)),
+        })
+    }
+
+    /// Polls a player and works out when to poll them next. Every poll that
+    /// gets an answer moves `next_poll_at`, even when the stats are unchanged,
+    /// so the caller writes these in batches.
+    async fn poll(&self, entry: UsernameEntry) -> (Outcome, Option<ScheduledPoll>) {
+        let outcome = self.fetch(&entry).await;
+
+        let now = DateTime::now();
+        let last_changed_at = match outcome {
+            Outcome::Updated => now,
+            // Players polled before scheduling existed have no change time;
+            // count their idle time from now.
+            Outcome::Unchanged | Outcome::Missing => entry.last_changed_at.unwrap_or(now),
+            // Try again next cycle.
+            Outcome::Failed => return (outcome, None),
+        };
+        let poll = ScheduledPoll {
+            display_name: entry.display_name,
+            game_mode: entry.game_mode,
+            last_changed_at,
+            next_poll_at: self.schedule.next_poll_at(now, last_changed_at),
+        };
+        (outcome, Some(poll))
+    }
+
+    /// Writes and clears `polls`.
+    async fn write_schedule(&self, polls: &mut Vec<ScheduledPoll>) {
+        if let Err(err) = self.store.schedule(polls).await {
+            println!("Failed to schedule {} players: {:?}", polls.len(), err);
+        }
+        polls.clear();
+    }
+
+    async fn fetch(&self, entry: &UsernameEntry) -> Outcome {
+        let UsernameEntry {
+            display_name,
+            game_mode,
+            missing_since,
+            ..
+        } = entry.clone();
+        println!("Fetching {} stats for {}", game_mode, display_name);
+
+        let hiscores = match self
+            .osrs
+            .user_hiscore(self.backend, game_mode, display_name.clone())
+            .await
+        {
+            Ok(hiscores) => hiscores,
+            Err(OsrsError::NotFound) => {
+                if missing_since.is_none() {
+                    println!("{} is not on the hiscores, marking missing", display_name);
+                    if let Err(err) = self
+                        .store
+                        .set_missing_since(&display_name, game_mode, Some(DateTime::now()))
+                        .await
+                    {
+                        println!("{:?}", err);
+                    }
+                }
+                return Outcome::Missing;
+            }
+            Err(err @ (OsrsError::HtmlLayout(_) | OsrsError::CsvLayout(_))) => {
+                eprintln!("ALERT: {} while loading {}", err, display_name);
+                return Outcome::Failed;
+            }
+            Err(err) => {
+                println!("Failed to load hiscores for {}: {}", display_name, err);
+                return Outcome::Failed;
+            }
+        };
+
+        println!("Found hiscores for {}", display_name);
+        if missing_since.is_some() {
+            if let Err(err) = self
+                .store
+                .set_missing_since(&display_name, game_mode, None)
+                .await
+            {
+                println!("{:?}", err);
+            }
+        }
+
+        let hash = db::stats_hash(&hiscores);
+        let key = (display_name.clone(), game_mode);
+        let cached = self.latest_hashes.lock().await.get(&key).copied();
+        let changed = match cached {
+            Some(cached) => cached != hash,
+            // No hash recorded yet, e.g. for snapshots taken before hashes
+            // were; compare with the snapshot itself this once.
+            None => match self.store.latest_snapshot(&display_name, game_mode).await {
+                Ok(old) => old.is_none_or(|old| old.stats != hiscores),
+                Err(err) => {
+                    println!("Failed to lookup previous entries: {}", err);
+                    return Outcome::Failed;
+                }
+            },
+        };
+
+        if changed {
+            println!("Hiscores different for {}, updating...", display_name);
+            let player_stats = StatEntry {
+                timestamp: DateTime::now(),
+                display_name: display_name.clone(),
+                game_mode,
+                stats: hiscores,
+            };
+            if let Some(err) = self.store.insert_snapshot(&player_stats).await.err() {
+                println!("{:?}", err);
+                return Outcome::Failed;
+            }
+        } else {
+            println!("Hiscores match for {}, skipping...", display_name);
+        }
+
+        if cached != Some(hash) {
+            if let Err(err) = self
+                .store
+                .set_latest_stats_hash(&display_name, game_mode, hash)
+                .await
+            {
+                println!("{:?}", err);
+            }
+            self.latest_hashes.lock().await.insert(key, hash);
+        }
+        if changed {
+            Outcome::Updated
+        } else {
+            Outcome::Unchanged
+        }
+    }
+}
+
+/// Polls every player that is due, `concurrency` at a time.
+async fn poll_due(poller: &Poller, concurrency: usize) -> Result<CycleStats, StoreError> {
+    let (sender, receiver) = mpsc::channel::<UsernameEntry>(concurrency);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    let mut workers: JoinSet<CycleStats> = JoinSet::new();
+    for _ in 0..concurrency {
+        let poller = poller.clone();
+        let receiver = receiver.clone();
+        workers.spawn(async move {
+            let mut cycle = CycleStats::default();
+            let mut polls = Vec::new();
+            loop {
+                let next = receiver.lock().await.recv().await;
+                let Some(entry) = next else {
+                    break;
+                };
+                let (outcome, poll) = poller.poll(entry).await;
+                cycle.record(outcome);
+                polls.extend(poll);
+                if polls.len() >= SCHEDULE_BATCH {
+                    poller.write_schedule(&mut polls).await;
+                }
+            }
+            if !polls.is_empty() {
+                poller.write_schedule(&mut polls).await;
+            }
+            cycle
+        });
+    }
+
+    for entry in poller.store.due_usernames(DateTime::now()).await? {
+        if sender.send(entry).await.is_err() {
+            break;
+        }
+    }
+    drop(sender);
+
+    let mut cycle = CycleStats::default();
+    while let Some(worker) = workers.join_next().await {
+        match worker {
+            Ok(worker) => cycle.merge(worker),
+            Err(err) => println!("Polling worker failed: {}", err),
+        }
+    }
+    Ok(cycle)
+}
+
+/// Keeps every known player's stats up to date, polling each one as their
+/// schedule comes due.
+pub async fn run(
+    store: &Arc<dyn Store>,
+    osrs: &OsrsClient,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let backend = match env::var("HISCORE_BACKEND") {
+        Ok(backend) => backend.parse()?,
+        Err(_) => HiscoreBackend::Lite,
+    };
+
+    let concurrency: usize = match env::var("SKILL_POLLING_CONCURRENCY") {
+        Ok(concurrency) => concurrency.parse()?,
+        Err(_) => 16,
+    };
+    if concurrency < 1 {
+        return Err("SKILL_POLLING_CONCURRENCY must be at least 1".into());
+    }
+    let poller = Poller::new(store, osrs, backend, Schedule::from_env()?).await?;
+
+    loop {
+        let started = Instant::now();
+        let cycle = poll_due(&poller, concurrency).await?;
+        let elapsed = started.elapsed();
+        println!(
+            "Polled {} players in {:.1}s ({:.2}/s): {} updated, {} unchanged, {} missing, {} failed",
+            cycle.total(),
+            elapsed.as_secs_f64(),
+            cycle.total() as f64 / elapsed.as_secs_f64(),
+            cycle.updated,
+            cycle.unchanged,
+            cycle.missing,
+            cycle.failed
+        );
+
+        println!("Waiting....");
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::memory::{MemoryData, MemoryStore},
+        fixtures::{self, lite_body},
+        osrs::layout::HiscoreLayout,
+    };
+
+    /// Hiscores that differ between players only in attack xp.
+    fn lite(attack_xp: u64) -> String {
+        let attack = format!("5000,50,{}", attack_xp);
+        lite_body(HiscoreLayout::current(), &[("attack", &attack)])
+    }
+
+    fn player(display_name: &str) -> UsernameEntry {
+        UsernameEntry {
+            display_name: display_name.to_string(),
+            game_mode: GameMode::Seasonal,
+            missing_since: None,
+            last_changed_at: None,
+            next_poll_at: None,
+        }
+    }
+
+    fn snapshot(display_name: &str, attack_xp: u64) -> StatEntry {
+        StatEntry {
+            timestamp: DateTime::from_millis(0),
+            display_name: display_name.to_string(),
+            game_mode: GameMode::Seasonal,
+            stats: HiscoreLayout::current().parse(&lite(attack_xp)).unwrap(),
+        }
+    }
+
+    async fn poll_once(store: &Arc<MemoryStore>, osrs: &OsrsClient) -> CycleStats {
+        let store: Arc<dyn Store> = store.clone();
+        let poller = Poller::new(&store, osrs, HiscoreBackend::Lite, Schedule::default())
+            .await
+            .unwrap();
+        poll_due(&poller, 2).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn stores_changed_stats_and_skips_unchanged() {
+        let store = Arc::new(MemoryStore::new(MemoryData {
+            usernames: vec![player("Grinder"), player("Idle")],
+            stats: vec![snapshot("Grinder", 1_000), snapshot("Idle", 2_000)],
+            ..MemoryData::default()
+        }));
+        let (osrs, _) = fixtures::hiscores(|request| match request.query["player"].as_str() {
+            "Grinder" => (200, lite(1_500)),
+            _ => (200, lite(2_000)),
+        })
+        .await;
+
+        let cycle = poll_once(&store, &osrs).await;
+        assert_eq!((cycle.updated, cycle.unchanged), (1, 1));
+        {
+            let data = store.data();
+            let snapshots =
+                |name: &str| data.stats.iter().filter(|s| s.display_name == name).count();
+            assert_eq!(snapshots("Grinder"), 2);
+            assert_eq!(snapshots("Idle"), 1);
+            assert_eq!(data.latest_stats.len(), 2);
+
+            let now = DateTime::now();
+            for entry in &data.usernames {
+                assert!(entry.next_poll_at.unwrap() > now);
+            }
+            let grinder = data.usernames.iter().find(|e| e.display_name == "Grinder");
+            assert!(grinder.unwrap().last_changed_at.is_some());
+        }
+
+        // Everyone was rescheduled, so nobody is due straight away.
+        assert_eq!(poll_once(&store, &osrs).await.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn marks_players_the_hiscores_do_not_know_missing() {
+        let store = Arc::new(MemoryStore::new(MemoryData {
+            usernames: vec![player("Renamed")],
+            ..MemoryData::default()
+        }));
+        let (osrs, _) = fixtures::hiscores(|_| (404, "Not Found".to_string())).await;
+
+        let cycle = poll_once(&store, &osrs).await;
+        assert_eq!(cycle.missing, 1);
+        let data = store.data();
+        assert!(data.stats.is_empty());
+        assert!(data.usernames[0].missing_since.is_some());
+        assert!(data.usernames[0].next_poll_at.is_some());
+    }
+}
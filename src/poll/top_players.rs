@@ -0,0 +1,267 @@
+use std::{env, ops::RangeInclusive, sync::Arc, time::Duration};
+
+use mongodb::bson::DateTime;
+
+use crate::{
+    config::Config,
+    db::{leaderboard_history::TopPlayerSnapshotEntry, Store, TopPlayerEntry},
+    osrs::{GameMode, Leaderboard, OsrsClient, OsrsError},
+};
+
+/// Leaderboards to track from `TOP_PLAYERS_LEADERBOARDS`, a comma separated
+/// list such as `activity:0,skill:0`. Defaults to the game mode's main
+/// leaderboard.
+fn leaderboards_from_env(game_mode: GameMode) -> Result<Vec<Leaderboard>, String> {
+    match env::var("TOP_PLAYERS_LEADERBOARDS") {
+        Ok(leaderboards) => leaderboards
+            .split(',')
+            .map(|leaderboard| leaderboard.trim().parse())
+            .collect(),
+        Err(_) => Ok(vec![Leaderboard::main(game_mode)]),
+    }
+}
+
+/// Pages of each leaderboard to fetch from `TOP_PLAYERS_PAGES`, e.g. `1-4`.
+fn pages_from_env() -> Result<RangeInclusive<usize>, String> {
+    match env::var("TOP_PLAYERS_PAGES") {
+        Ok(pages) => {
+            let (first, last) = pages
+                .split_once('-')
+                .ok_or_else(|| format!("expected first-last, got {}", pages))?;
+            let first = first.parse().map_err(|_| format!("bad page {}", first))?;
+            let last = last.parse().map_err(|_| format!("bad page {}", last))?;
+            Ok(first..=last)
+        }
+        Err(_) => Ok(1..=4),
+    }
+}
+
+/// Fetches one leaderboard, returning every entry in rank order, or `None` if
+/// a page failed.
+async fn build_leaderboard(
+    osrs: &OsrsClient,
+    game_mode: GameMode,
+    leaderboard: Leaderboard,
+    pages: RangeInclusive<usize>,
+) -> Option<Vec<TopPlayerEntry>> {
+    let mut entries = Vec::new();
+    for i in pages {
+        match osrs.hiscores_index(game_mode, leaderboard, i).await {
+            Ok(page) => {
+                let is_last = page.is_last;
+                entries.extend(page.rows.into_iter().map(|row| TopPlayerEntry {
+                    display_name: row.name().to_string(),
+                    game_mode,
+                    category: leaderboard.category,
+                    table: leaderboard.table,
+                    rank: Some(row.rank()),
+                    score: row.score(),
+                    level: row.level(),
+                }));
+                if is_last {
+                    return Some(entries);
+                }
+            }
+            Err(OsrsError::RateLimited { retry_after }) => {
+                let wait = retry_after.unwrap_or(Duration::from_secs(60));
+                println!("Rate limited, backing off for {:?}", wait);
+                tokio::time::sleep(wait).await;
+                return None;
+            }
+            Err(err @ (OsrsError::HtmlLayout(_) | OsrsError::CsvLayout(_))) => {
+                eprintln!("ALERT: {} on {} page {}", err, leaderboard, i);
+                return None;
+            }
+            Err(err) => {
+                println!("Failed to load {} page {}: {}", leaderboard, i, err);
+                return None;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+    Some(entries)
+}
+
+/// Fetches every leaderboard in `leaderboards` and swaps them in together,
+/// recording a snapshot of each. The previous leaderboards stay if a page
+/// fails or nothing was found.
+async fn refresh(
+    store: &dyn Store,
+    osrs: &OsrsClient,
+    game_mode: GameMode,
+    leaderboards: &[Leaderboard],
+    pages: RangeInclusive<usize>,
+) {
+    println!("Updating {} top players...", game_mode);
+    let snapshot_at = DateTime::now();
+
+    let mut built = Vec::new();
+    for &leaderboard in leaderboards {
+        match build_leaderboard(osrs, game_mode, leaderboard, pages.clone()).await {
+            Some(entries) => built.push((leaderboard, entries)),
+            None => break,
+        }
+    }
+
+    if built.len() < leaderboards.len() {
+        println!("Top players incomplete, keeping the previous snapshot");
+    } else if built.iter().all(|(_, entries)| entries.is_empty()) {
+        println!("No top players found, keeping the previous snapshot");
+    } else {
+        // Every leaderboard is swapped in together.
+        let entries: Vec<TopPlayerEntry> = built
+            .iter()
+            .flat_map(|(_, entries)| entries.iter().cloned())
+            .collect();
+        match store
+            .replace_top_players(game_mode, leaderboards, &entries)
+            .await
+        {
+            Ok(_) => println!("Top players updated"),
+            Err(err) => println!("Failed to swap in top players: {:?}", err),
+        }
+
+        for (leaderboard, entries) in built {
+            let snapshot: Vec<TopPlayerSnapshotEntry> = entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, entry)| TopPlayerSnapshotEntry {
+                    snapshot_at,
+                    game_mode,
+                    category: leaderboard.category,
+                    table: leaderboard.table,
+                    rank: entry.rank.unwrap_or(i as u64 + 1),
+                    display_name: entry.display_name,
+                    score: entry.score,
+                })
+                .collect();
+            if let Err(err) = store.insert_top_players_snapshot(&snapshot).await {
+                println!("Failed to record {} snapshot: {:?}", leaderboard, err);
+            }
+        }
+    }
+}
+
+/// Refreshes the configured leaderboards every 15 minutes, keeping a snapshot
+/// of each refresh.
+pub async fn run(
+    config: &Config,
+    store: &Arc<dyn Store>,
+    osrs: &OsrsClient,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let game_mode = config.game_mode;
+    let leaderboards = leaderboards_from_env(game_mode)?;
+    let pages = pages_from_env()?;
+
+    loop {
+        refresh(
+            store.as_ref(),
+            osrs,
+            game_mode,
+            &leaderboards,
+            pages.clone(),
+        )
+        .await;
+
+        println!("Waiting....");
+        tokio::time::sleep(Duration::from_secs(60 * 15)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::memory::{MemoryData, MemoryStore},
+        fixtures,
+        osrs::Category,
+    };
+
+    const BOSSES: Leaderboard = Leaderboard {
+        category: Category::Activity,
+        table: 5,
+    };
+
+    fn entry(display_name: &str, game_mode: GameMode, leaderboard: Leaderboard) -> TopPlayerEntry {
+        TopPlayerEntry {
+            display_name: display_name.to_string(),
+            game_mode,
+            category: leaderboard.category,
+            table: leaderboard.table,
+            rank: Some(1),
+            score: 10,
+            level: None,
+        }
+    }
+
+    fn names(store: &MemoryStore) -> Vec<String> {
+        let mut names: Vec<String> = store
+            .data()
+            .top_players
+            .iter()
+            .map(|entry| entry.display_name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn previous() -> MemoryStore {
+        MemoryStore::new(MemoryData {
+            top_players: vec![
+                entry("Old Leader", GameMode::Seasonal, Leaderboard::LEAGUE_POINTS),
+                entry("Old Boss", GameMode::Seasonal, BOSSES),
+                entry("Main Leader", GameMode::Regular, Leaderboard::LEAGUE_POINTS),
+            ],
+            ..MemoryData::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn swaps_in_every_leaderboard_of_the_game_mode() {
+        let store = previous();
+        let (osrs, _) = fixtures::hiscores(|request| match request.query["table"].as_str() {
+            "0" => (
+                200,
+                fixtures::leaderboard_page(&[(1, "Leader", 900), (2, "Runner", 800)], None),
+            ),
+            _ => (200, fixtures::leaderboard_page(&[(1, "Slayer", 50)], None)),
+        })
+        .await;
+
+        let leaderboards = [Leaderboard::LEAGUE_POINTS, BOSSES];
+        refresh(&store, &osrs, GameMode::Seasonal, &leaderboards, 1..=1).await;
+
+        assert_eq!(names(&store), ["Leader", "Main Leader", "Runner", "Slayer"]);
+        let history = store.data().top_players_history.clone();
+        assert_eq!(history.len(), 3);
+        assert!(history
+            .iter()
+            .all(|entry| entry.snapshot_at == history[0].snapshot_at));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_previous_leaderboards_when_a_page_fails() {
+        let store = previous();
+        let (osrs, _) = fixtures::hiscores(|request| match request.query["table"].as_str() {
+            "0" => (200, fixtures::leaderboard_page(&[(1, "Leader", 900)], None)),
+            _ => (500, String::new()),
+        })
+        .await;
+
+        let leaderboards = [Leaderboard::LEAGUE_POINTS, BOSSES];
+        refresh(&store, &osrs, GameMode::Seasonal, &leaderboards, 1..=1).await;
+
+        assert_eq!(names(&store), ["Main Leader", "Old Boss", "Old Leader"]);
+        assert!(store.data().top_players_history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_the_previous_leaderboards_when_nothing_is_found() {
+        let store = previous();
+        let (osrs, _) = fixtures::hiscores(|_| (200, fixtures::leaderboard_page(&[], None))).await;
+
+        refresh(&store, &osrs, GameMode::Seasonal, &[BOSSES], 1..=1).await;
+
+        assert_eq!(names(&store), ["Main Leader", "Old Boss", "Old Leader"]);
+    }
+}